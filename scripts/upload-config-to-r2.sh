@@ -60,6 +60,11 @@ FILES=(
   "doctor-names.json"
   "full-name-overrides.json"
   "doctor-photos.json"
+  "shift-coverage.config.json"
+  "shift-stats.config.json"
+  "schedule-rules.config.json"
+  "person-groups.config.json"
+  "calendar-days.config.json"
 )
 
 echo ""
@@ -100,6 +105,11 @@ if [ "$USE_REMOTE" = true ]; then
   echo "  npx wrangler r2 object get ${BUCKET_NAME}/doctor-names.json --remote"
   echo "  npx wrangler r2 object get ${BUCKET_NAME}/full-name-overrides.json --remote"
   echo "  npx wrangler r2 object get ${BUCKET_NAME}/doctor-photos.json --remote"
+  echo "  npx wrangler r2 object get ${BUCKET_NAME}/shift-coverage.config.json --remote"
+  echo "  npx wrangler r2 object get ${BUCKET_NAME}/shift-stats.config.json --remote"
+  echo "  npx wrangler r2 object get ${BUCKET_NAME}/schedule-rules.config.json --remote"
+  echo "  npx wrangler r2 object get ${BUCKET_NAME}/person-groups.config.json --remote"
+  echo "  npx wrangler r2 object get ${BUCKET_NAME}/calendar-days.config.json --remote"
 else
   echo "  npx wrangler r2 object get ${BUCKET_NAME}/shift-display.config.json"
   echo "  npx wrangler r2 object get ${BUCKET_NAME}/shift-styling.config.json"
@@ -107,4 +117,9 @@ else
   echo "  npx wrangler r2 object get ${BUCKET_NAME}/doctor-names.json"
   echo "  npx wrangler r2 object get ${BUCKET_NAME}/full-name-overrides.json"
   echo "  npx wrangler r2 object get ${BUCKET_NAME}/doctor-photos.json"
+  echo "  npx wrangler r2 object get ${BUCKET_NAME}/shift-coverage.config.json"
+  echo "  npx wrangler r2 object get ${BUCKET_NAME}/shift-stats.config.json"
+  echo "  npx wrangler r2 object get ${BUCKET_NAME}/schedule-rules.config.json"
+  echo "  npx wrangler r2 object get ${BUCKET_NAME}/person-groups.config.json"
+  echo "  npx wrangler r2 object get ${BUCKET_NAME}/calendar-days.config.json"
 fi
@@ -0,0 +1,55 @@
+#!/usr/bin/env node
+// Local stand-in for a schedule change webhook consumer.
+// Usage: SCHEDULE_WEBHOOK_SECRET=dev-secret node scripts/webhook-receiver.mjs [port]
+//
+// Verifies the X-Schedule-Signature header the Worker sends
+// (HMAC-SHA256 over "<X-Schedule-Timestamp>.<raw body>") and prints the payload.
+
+import { createHmac, timingSafeEqual } from 'node:crypto'
+import { createServer } from 'node:http'
+
+const port = Number(process.argv[2] ?? 8788)
+const secret = process.env.SCHEDULE_WEBHOOK_SECRET
+
+if (!secret) {
+  console.error('SCHEDULE_WEBHOOK_SECRET must be set to verify signatures')
+  process.exit(1)
+}
+
+function isValidSignature(timestamp, body, header) {
+  if (!timestamp || !header?.startsWith('sha256=')) {
+    return false
+  }
+
+  const expected = createHmac('sha256', secret).update(`${timestamp}.${body}`).digest('hex')
+  const received = header.slice('sha256='.length)
+
+  return (
+    expected.length === received.length &&
+    timingSafeEqual(Buffer.from(expected), Buffer.from(received))
+  )
+}
+
+createServer((req, res) => {
+  let body = ''
+  req.on('data', (chunk) => {
+    body += chunk
+  })
+  req.on('end', () => {
+    const timestamp = req.headers['x-schedule-timestamp']
+    const signature = req.headers['x-schedule-signature']
+    const valid = isValidSignature(timestamp, body, signature)
+
+    console.log(`${new Date().toISOString()} ${req.method} ${req.url} signature=${valid ? 'ok' : 'INVALID'}`)
+    try {
+      console.dir(JSON.parse(body), { depth: null })
+    } catch {
+      console.log(body)
+    }
+
+    res.writeHead(valid ? 204 : 401)
+    res.end()
+  })
+}).listen(port, () => {
+  console.log(`Webhook receiver listening on http://localhost:${port}`)
+})
@@ -21,7 +21,7 @@ export function NameCellContent({
 }: NameCellContentProps) {
   const { fullNameOverrideSet } = useRuntimeConfig()
   const normalizedName = person.resolvedName.trim().toLowerCase()
-  const shouldForceFullName = fullNameOverrideSet.has(normalizedName)
+  const shouldForceFullName = person.keepFullName || fullNameOverrideSet.has(normalizedName)
   const showFullNameInCompact = isHorizontalScrollActive && shouldForceFullName
 
   const { ref: nameRef, style } = useAdaptiveCompactName(
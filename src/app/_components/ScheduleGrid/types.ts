@@ -69,6 +69,7 @@ export interface PersonWithDisplay {
   displayName: string
   resolvedName: string
   pseudonym: string | null
+  keepFullName: boolean
   originalIndex: number
   photoUrl: string | null
 }
@@ -34,9 +34,20 @@ export function preparePeopleWithNames(
   ) => DoctorDisplayName,
   doctorPhotos?: Record<string, string>
 ): PersonWithDisplay[] {
-  return people
+  // The Worker sends people in group order; only months cached before it resolved
+  // names (no displayName) still need sorting here
+  const serverOrdered = people.every(person => Boolean(person.displayName))
+
+  const prepared = people
     .map((person, index) => {
-      const displayInfo = resolveDisplayName(person.id, person.name)
+      // Names resolved by the Worker win; older cached months fall back to the local mapping
+      const displayInfo: DoctorDisplayName = person.displayName
+        ? {
+            name: person.resolvedName ?? '',
+            pseudonym: person.pseudonym,
+            display: person.displayName,
+          }
+        : resolveDisplayName(person.id, person.name)
       const photoFilename = doctorPhotos?.[person.id]
       const photoUrl = photoFilename ? `/doctor-photos/${photoFilename}` : null
 
@@ -45,12 +56,16 @@ export function preparePeopleWithNames(
         displayName: displayInfo.display,
         resolvedName: displayInfo.name || displayInfo.display,
         pseudonym: displayInfo.pseudonym ?? null,
+        keepFullName: person.keepFullName ?? false,
         originalIndex: index,
         photoUrl,
       }
     })
     .filter(person => !person.displayName.startsWith('zzz_'))
-    .sort((a, b) => a.displayName.localeCompare(b.displayName, 'it'))
+
+  return serverOrdered
+    ? prepared
+    : prepared.sort((a, b) => a.displayName.localeCompare(b.displayName, 'it'))
 }
 
 export function calculateNameColumnWidth(
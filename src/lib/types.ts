@@ -3,6 +3,19 @@
 export type Person = {
   id: string
   name: string
+  // Resolved by the Worker from doctor-names.json / full-name-overrides.json
+  displayName?: string
+  resolvedName?: string
+  pseudonym?: string
+  keepFullName?: boolean
+  // ID of the person's group from person-groups.config.json
+  group?: string
+}
+
+export type PersonGroup = {
+  id: string
+  label: string
+  members: string[] // person IDs, in display order
 }
 
 export type DayCell = {
@@ -21,6 +34,30 @@ export type MonthShifts = {
   codes?: string[]
   // Mapping of shift codes to full shift names (e.g., "RATM" -> "RATM 8:00AM - 2:00PM")
   shiftNames?: Record<string, string>
+  // Colours MetricAid assigns to codes (fallback for codes missing from shift-colors.json)
+  upstreamColors?: Record<string, string>
+  // Overnight shifts from the previous month's last day that run into the 1st, aligned with people
+  carryIn?: (string[] | null)[]
+  // Calendar metadata, one entry per day of the month
+  days?: DayInfo[]
+  // Configured groups; people are ordered by group, then by name
+  groups?: PersonGroup[]
+}
+
+// Returned with ?styled=1
+export type ShiftStyles = {
+  codes: Record<string, { background: string; text: string; source: 'config' | 'upstream' | 'generated' }>
+  // Entries rows[row][day][entry] matched by the conditional underline rule
+  cells: { row: number; day: number; entry: number; underline: boolean }[]
+}
+
+export type DayInfo = {
+  date: string // "YYYY-MM-DD"
+  weekday: number // 0=Sunday through 6=Saturday
+  isoWeek: string // "YYYY-Www"
+  holiday: boolean
+  holidayName?: string
+  closure?: string
 }
 
 // Error response from Worker
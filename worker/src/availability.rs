@@ -0,0 +1,231 @@
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use worker::*;
+
+use crate::people::{collect_person_shifts, PersonShift};
+use crate::timezone;
+use crate::{
+    build_success_response, error_response, get_config, query_param, range, response_ttl,
+    MonthShifts, Person, ScheduleVersion, ShiftCell,
+};
+
+#[derive(Serialize)]
+struct TimeWindow {
+    start: String,
+    end: String,
+}
+
+/// Someone who already works that day, but not during the target window
+#[derive(Serialize)]
+struct AvailablePerson {
+    person: Person,
+    shifts: Vec<PersonShift>,
+}
+
+#[derive(Serialize)]
+struct AvailabilityReport {
+    date: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    window: Option<TimeWindow>,
+    /// No shift running at any time on `date`, including overnight ones from the day before,
+    /// and none overlapping `window`
+    free: Vec<Person>,
+    /// Shifts running on `date`, none overlapping `window` (only with `code`)
+    available: Vec<AvailablePerson>,
+}
+
+/// API endpoint: who could take a shift on `date` (optionally a specific `code`)
+pub async fn handle_free_people(req: Request, ctx: RouteContext<Context>) -> Result<Response> {
+    let config = match get_config(&ctx.env) {
+        Ok(cfg) => cfg,
+        Err(e) => return error_response("CONFIG_ERROR", &e.to_string(), 500),
+    };
+
+    let url = req.url()?;
+    let date = match query_param(&url, "date") {
+        Some(value) => match NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => {
+                return error_response("INVALID_DATE", "Invalid date. Expected YYYY-MM-DD", 400)
+            }
+        },
+        None => return error_response("MISSING_PARAM", "Missing required parameter: date", 400),
+    };
+    let code = query_param(&url, "code")
+        .map(|code| code.trim().to_string())
+        .filter(|code| !code.is_empty());
+
+    // Overnight shifts from the day before (or into the day after) can overlap the window
+    let (Some(window_start), Some(window_end)) = (date.pred_opt(), date.succ_opt()) else {
+        return error_response("INVALID_DATE", "Date is out of range", 400);
+    };
+    let months = range::months_in_range(window_start, window_end);
+    let results = range::load_months(&ctx, &config, &months, ScheduleVersion::Live).await;
+
+    let target_ym = date.format("%Y-%m").to_string();
+    let mut statuses: HashSet<&'static str> = HashSet::new();
+    let mut month_map: HashMap<String, MonthShifts> = HashMap::new();
+    for (ym, result) in months.iter().zip(results) {
+        match result {
+            Ok((month_shifts, status)) => {
+                statuses.insert(status);
+                month_map.insert(ym.clone(), month_shifts);
+            }
+            Err(failure) => return failure.into_response(),
+        }
+    }
+    let Some(target_month) = month_map.get(&target_ym) else {
+        return error_response("INVALID_DATE", "Date is out of range", 400);
+    };
+
+    let window = match &code {
+        Some(code) => match resolve_code_window(target_month, code, date) {
+            Some(window) => Some(window),
+            None => {
+                return error_response(
+                    "UNKNOWN_CODE",
+                    &format!("No shift times known for code {} in {}", code, target_ym),
+                    404,
+                )
+            }
+        },
+        None => None,
+    };
+
+    let date_str = date.format("%Y-%m-%d").to_string();
+    let day = match (
+        date.and_hms_opt(0, 0, 0)
+            .and_then(timezone::from_rome_local),
+        window_end
+            .and_hms_opt(0, 0, 0)
+            .and_then(timezone::from_rome_local),
+    ) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return error_response("INVALID_DATE", "Date is out of range", 400),
+    };
+
+    let mut free = Vec::new();
+    let mut available = Vec::new();
+    for person in &target_month.people {
+        let shifts: Vec<PersonShift> = months
+            .iter()
+            .filter_map(|ym| month_map.get(ym))
+            .filter_map(|month| collect_person_shifts(month, &person.id, window_start, window_end))
+            .flat_map(|(_, shifts)| shifts)
+            .collect();
+
+        // Everyone is checked against the window, which may run into the next day
+        if window.is_some_and(|window| {
+            shifts
+                .iter()
+                .any(|shift| overlaps(shift, &date_str, window))
+        }) {
+            continue;
+        }
+
+        // Anything running into the day, e.g. a night shift from the day before, rules out "free"
+        let on_day: Vec<PersonShift> = shifts
+            .into_iter()
+            .filter(|shift| overlaps(shift, &date_str, day))
+            .collect();
+        if on_day.is_empty() {
+            free.push(person.clone());
+        } else if window.is_some() {
+            available.push(AvailablePerson {
+                person: person.clone(),
+                shifts: on_day,
+            });
+        }
+    }
+
+    let report = AvailabilityReport {
+        date: date_str,
+        code,
+        window: window.map(|(start, end)| TimeWindow {
+            start: timezone::format_timestamp(&start),
+            end: timezone::format_timestamp(&end),
+        }),
+        free,
+        available,
+    };
+
+    let cache_status = if statuses.contains("STALE") {
+        "STALE"
+    } else if statuses.len() == 1 && statuses.contains("HIT") {
+        "HIT"
+    } else {
+        "MISS"
+    };
+
+    let json = serde_json::to_string(&report)?;
+    let if_none_match = req.headers().get("If-None-Match")?;
+    build_success_response(
+        json,
+        response_ttl(&config, cache_status),
+        cache_status,
+        if_none_match.as_deref(),
+    )
+}
+
+/// Time window of `code` on `date`: taken from someone holding it that day, or
+/// else from any occurrence in the month moved onto `date`.
+fn resolve_code_window(
+    month_shifts: &MonthShifts,
+    code: &str,
+    date: NaiveDate,
+) -> Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+    let mut fallback = None;
+    for row in &month_shifts.rows {
+        for cell in row.iter().flatten().flatten() {
+            let ShiftCell::Timed(timed) = cell else {
+                continue;
+            };
+            if !timed.code.eq_ignore_ascii_case(code) {
+                continue;
+            }
+            let (Some(start), Some(end)) = (
+                parse_shift_time(timed.start.as_deref()),
+                parse_shift_time(timed.end.as_deref()),
+            ) else {
+                continue;
+            };
+            if start.date_naive() == date {
+                return Some((start, end));
+            }
+            fallback.get_or_insert((start, end));
+        }
+    }
+
+    // Move the wall-clock times, so the window keeps its hours across a DST change
+    let (start, end) = fallback?;
+    let shift = date.signed_duration_since(start.date_naive());
+    Some((
+        timezone::from_rome_local(start.naive_local() + shift)?,
+        timezone::from_rome_local(end.naive_local() + shift)?,
+    ))
+}
+
+/// Untimed shifts can't be ruled out, so they count as overlapping anything on their own day
+fn overlaps(
+    shift: &PersonShift,
+    date: &str,
+    (start, end): (DateTime<FixedOffset>, DateTime<FixedOffset>),
+) -> bool {
+    match (
+        parse_shift_time(shift.start.as_deref()),
+        parse_shift_time(shift.end.as_deref()),
+    ) {
+        (Some(shift_start), Some(shift_end)) => {
+            let shift_end = shift_end.max(shift_start + Duration::minutes(1));
+            shift_start < end && start < shift_end
+        }
+        _ => shift.date == date,
+    }
+}
+
+fn parse_shift_time(value: Option<&str>) -> Option<DateTime<FixedOffset>> {
+    timezone::parse_timestamp(value?)
+}
@@ -0,0 +1,427 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use worker::kv::KvStore;
+use worker::*;
+
+const DEFAULT_BACKEND: &str = "cache";
+const KV_BINDING: &str = "SCHEDULE_CACHE_KV";
+// KV rejects expirations shorter than a minute
+const KV_MIN_TTL_SECONDS: u64 = 60;
+// Synthetic origin for Cache API keys; it is never fetched
+const EDGE_CACHE_ORIGIN: &str = "https://schedule-viewer.internal/cache/";
+
+// Per-isolate L1 shared by every namespace (keys are prefixed, e.g. `schedule:2025-01`)
+static L1_CACHE: Lazy<MemoryBackend> = Lazy::new(MemoryBackend::new);
+
+/// A cached payload plus the time it was produced upstream.
+/// `stored_at` travels with the entry so every isolate ages it the same way.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CacheEntry {
+    pub value: String,
+    #[serde(rename = "storedAt")]
+    pub stored_at: DateTime<Utc>,
+}
+
+impl CacheEntry {
+    pub fn new(value: String) -> Self {
+        CacheEntry {
+            value,
+            stored_at: Utc::now(),
+        }
+    }
+
+    pub fn age_seconds(&self) -> i64 {
+        Utc::now()
+            .signed_duration_since(self.stored_at)
+            .num_seconds()
+    }
+
+    /// Grade the entry by age: fresh below the TTL, then the two stale windows past it
+    pub fn freshness(
+        &self,
+        ttl_seconds: u64,
+        stale_while_revalidate_seconds: u64,
+        stale_if_error_seconds: u64,
+    ) -> Freshness {
+        let age_seconds = self.age_seconds();
+        let ttl = ttl_seconds as i64;
+
+        if age_seconds < ttl {
+            Freshness::Fresh
+        } else if age_seconds < ttl + stale_while_revalidate_seconds as i64 {
+            Freshness::Revalidate
+        } else if age_seconds < ttl + stale_if_error_seconds as i64 {
+            Freshness::StaleIfError
+        } else {
+            Freshness::Expired
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Freshness {
+    /// Younger than the TTL
+    Fresh,
+    /// Past the TTL but inside the stale-while-revalidate window
+    Revalidate,
+    /// Past the revalidate window but still usable if upstream fails
+    StaleIfError,
+    /// Too old to serve
+    Expired,
+}
+
+/// Storage for cache entries. `ttl_seconds` is how long the backend should retain
+/// the entry; freshness decisions are made by the caller from `stored_at`.
+pub trait CacheBackend {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>>;
+    async fn put(&self, key: &str, entry: &CacheEntry, ttl_seconds: u64) -> Result<()>;
+}
+
+/// In-memory backend. Used as the per-isolate L1, and as a stand-in L2 in tests
+/// and local dev.
+pub struct MemoryBackend {
+    entries: RwLock<HashMap<String, CacheEntry>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        MemoryBackend {
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn read(&self, key: &str) -> Option<CacheEntry> {
+        self.entries.read().ok()?.get(key).cloned()
+    }
+
+    fn write(&self, key: &str, entry: CacheEntry) {
+        if let Ok(mut entries) = self.entries.write() {
+            entries.insert(key.to_string(), entry);
+        }
+    }
+
+    fn remove_prefix(&self, prefix: &str) {
+        if let Ok(mut entries) = self.entries.write() {
+            entries.retain(|key, _| !key.starts_with(prefix));
+        }
+    }
+}
+
+impl Default for MemoryBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CacheBackend for MemoryBackend {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>> {
+        Ok(self.read(key))
+    }
+
+    async fn put(&self, key: &str, entry: &CacheEntry, _ttl_seconds: u64) -> Result<()> {
+        self.write(key, entry.clone());
+        Ok(())
+    }
+}
+
+/// Workers KV: global, eventually consistent (writes can take ~60s to propagate)
+pub struct KvBackend {
+    store: KvStore,
+}
+
+impl CacheBackend for KvBackend {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>> {
+        let raw = self.store.get(key).text().await?;
+        Ok(raw.and_then(|json| serde_json::from_str(&json).ok()))
+    }
+
+    async fn put(&self, key: &str, entry: &CacheEntry, ttl_seconds: u64) -> Result<()> {
+        let json = serde_json::to_string(entry)?;
+        self.store
+            .put(key, json)?
+            .expiration_ttl(ttl_seconds.max(KV_MIN_TTL_SECONDS))
+            .execute()
+            .await?;
+        Ok(())
+    }
+}
+
+/// Workers Cache API: shared by every isolate in a data center, no binding needed.
+/// Note that the Cache API is a no-op on `*.workers.dev` hostnames.
+pub struct EdgeCacheBackend {
+    cache: Cache,
+}
+
+impl EdgeCacheBackend {
+    fn cache_url(key: &str) -> String {
+        format!("{}{}", EDGE_CACHE_ORIGIN, key)
+    }
+}
+
+impl CacheBackend for EdgeCacheBackend {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>> {
+        match self.cache.get(Self::cache_url(key), false).await? {
+            Some(mut response) => {
+                let json = response.text().await?;
+                Ok(serde_json::from_str(&json).ok())
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put(&self, key: &str, entry: &CacheEntry, ttl_seconds: u64) -> Result<()> {
+        let headers = Headers::new();
+        headers.set("Content-Type", "application/json")?;
+        headers.set(
+            "Cache-Control",
+            &format!("public, max-age={}", ttl_seconds.max(1)),
+        )?;
+        let response = Response::ok(serde_json::to_string(entry)?)?.with_headers(headers);
+        self.cache.put(Self::cache_url(key), response).await
+    }
+}
+
+/// The L2 selected by `SCHEDULE_CACHE_BACKEND` (`cache`, `kv` or `none`)
+pub enum SharedBackend {
+    Edge(EdgeCacheBackend),
+    Kv(KvBackend),
+    Disabled,
+}
+
+impl SharedBackend {
+    pub fn from_env(env: &Env) -> Self {
+        let backend = env
+            .var("SCHEDULE_CACHE_BACKEND")
+            .map(|v| v.to_string().trim().to_lowercase())
+            .unwrap_or_else(|_| DEFAULT_BACKEND.to_string());
+
+        match backend.as_str() {
+            "kv" => match env.kv(KV_BINDING) {
+                Ok(store) => SharedBackend::Kv(KvBackend { store }),
+                Err(e) => {
+                    console_error!(
+                        "{} not configured: {:?}, shared cache disabled",
+                        KV_BINDING,
+                        e
+                    );
+                    SharedBackend::Disabled
+                }
+            },
+            "cache" => SharedBackend::Edge(EdgeCacheBackend {
+                cache: Cache::default(),
+            }),
+            "none" => SharedBackend::Disabled,
+            other => {
+                console_error!(
+                    "Unknown SCHEDULE_CACHE_BACKEND {:?}, shared cache disabled",
+                    other
+                );
+                SharedBackend::Disabled
+            }
+        }
+    }
+}
+
+impl CacheBackend for SharedBackend {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>> {
+        match self {
+            SharedBackend::Edge(backend) => backend.get(key).await,
+            SharedBackend::Kv(backend) => backend.get(key).await,
+            SharedBackend::Disabled => Ok(None),
+        }
+    }
+
+    async fn put(&self, key: &str, entry: &CacheEntry, ttl_seconds: u64) -> Result<()> {
+        match self {
+            SharedBackend::Edge(backend) => backend.put(key, entry, ttl_seconds).await,
+            SharedBackend::Kv(backend) => backend.put(key, entry, ttl_seconds).await,
+            SharedBackend::Disabled => Ok(()),
+        }
+    }
+}
+
+/// Two-level cache: the per-isolate map in front of a shared backend.
+/// Shared backend failures are logged and treated as misses.
+pub struct TieredCache<'a, B: CacheBackend> {
+    l1: &'a MemoryBackend,
+    l2: B,
+    namespace: &'static str,
+}
+
+impl TieredCache<'static, SharedBackend> {
+    /// Cache for `namespace` backed by the process L1 and the configured L2
+    pub fn from_env(env: &Env, namespace: &'static str) -> Self {
+        TieredCache::new(&L1_CACHE, SharedBackend::from_env(env), namespace)
+    }
+}
+
+impl<'a, B: CacheBackend> TieredCache<'a, B> {
+    pub fn new(l1: &'a MemoryBackend, l2: B, namespace: &'static str) -> Self {
+        TieredCache { l1, l2, namespace }
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}:{}", self.namespace, key)
+    }
+
+    /// Look up an entry. L1 entries younger than `fresh_seconds` are returned
+    /// without touching L2; otherwise the newer of the L1 and L2 copies wins,
+    /// so a refresh made by another isolate replaces a stale local copy.
+    pub async fn get(&self, key: &str, fresh_seconds: u64) -> Option<CacheEntry> {
+        let key = self.key(key);
+        let local = self.l1.read(&key);
+        if let Some(entry) = &local {
+            if entry.age_seconds() < fresh_seconds as i64 {
+                return local;
+            }
+        }
+
+        let shared = match self.l2.get(&key).await {
+            Ok(shared) => shared,
+            Err(e) => {
+                console_error!("Shared cache read failed for {}: {:?}", key, e);
+                None
+            }
+        };
+
+        match (local, shared) {
+            (Some(local), Some(shared)) if shared.stored_at > local.stored_at => {
+                self.l1.write(&key, shared.clone());
+                Some(shared)
+            }
+            (None, Some(shared)) => {
+                self.l1.write(&key, shared.clone());
+                Some(shared)
+            }
+            (local, _) => local,
+        }
+    }
+
+    /// Store a value in both levels; L2 keeps it for `retain_seconds`
+    pub async fn put(&self, key: &str, value: String, retain_seconds: u64) -> CacheEntry {
+        let key = self.key(key);
+        let entry = CacheEntry::new(value);
+        self.l1.write(&key, entry.clone());
+        if let Err(e) = self.l2.put(&key, &entry, retain_seconds).await {
+            console_error!("Shared cache write failed for {}: {:?}", key, e);
+        }
+        entry
+    }
+
+    /// Drop every L1 entry in this namespace (L2 entries age out on their own)
+    pub fn clear_local(&self) {
+        self.l1.remove_prefix(&format!("{}:", self.namespace));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use futures::executor::block_on;
+
+    const TTL: u64 = 300;
+    const STALE_WHILE_REVALIDATE: u64 = 300;
+    const STALE_IF_ERROR: u64 = 86_400;
+    const KEY: &str = "schedule:2025-01";
+
+    fn aged(value: &str, age_seconds: i64) -> CacheEntry {
+        CacheEntry {
+            value: value.to_string(),
+            stored_at: Utc::now() - Duration::seconds(age_seconds),
+        }
+    }
+
+    fn freshness_at(age_seconds: i64) -> Freshness {
+        let l1 = MemoryBackend::new();
+        l1.write(KEY, aged("cached", age_seconds));
+        let cache = TieredCache::new(&l1, MemoryBackend::new(), "schedule");
+        block_on(cache.get("2025-01", TTL))
+            .expect("entry should be found")
+            .freshness(TTL, STALE_WHILE_REVALIDATE, STALE_IF_ERROR)
+    }
+
+    #[test]
+    fn fresh_l1_entry_is_served_without_consulting_l2() {
+        let l1 = MemoryBackend::new();
+        let l2 = MemoryBackend::new();
+        l1.write(KEY, aged("local", 10));
+        l2.write(KEY, aged("shared", 0));
+        let cache = TieredCache::new(&l1, l2, "schedule");
+
+        let entry = block_on(cache.get("2025-01", TTL)).unwrap();
+        assert_eq!(entry.value, "local");
+    }
+
+    #[test]
+    fn l1_miss_falls_back_to_l2_and_fills_l1() {
+        let l1 = MemoryBackend::new();
+        let l2 = MemoryBackend::new();
+        l2.write(KEY, aged("shared", 10));
+        let cache = TieredCache::new(&l1, l2, "schedule");
+
+        let entry = block_on(cache.get("2025-01", TTL)).unwrap();
+        assert_eq!(entry.value, "shared");
+        assert_eq!(l1.read(KEY).unwrap().value, "shared");
+    }
+
+    #[test]
+    fn stale_l1_entry_is_replaced_by_newer_l2_copy() {
+        let l1 = MemoryBackend::new();
+        let l2 = MemoryBackend::new();
+        l1.write(KEY, aged("local", 600));
+        l2.write(KEY, aged("shared", 30));
+        let cache = TieredCache::new(&l1, l2, "schedule");
+
+        let entry = block_on(cache.get("2025-01", TTL)).unwrap();
+        assert_eq!(entry.value, "shared");
+        assert_eq!(l1.read(KEY).unwrap().value, "shared");
+    }
+
+    #[test]
+    fn stale_l1_entry_is_kept_when_l2_is_older_or_missing() {
+        let l1 = MemoryBackend::new();
+        let l2 = MemoryBackend::new();
+        l1.write(KEY, aged("local", 600));
+        l2.write(KEY, aged("shared", 900));
+        let cache = TieredCache::new(&l1, l2, "schedule");
+        assert_eq!(block_on(cache.get("2025-01", TTL)).unwrap().value, "local");
+
+        let l1 = MemoryBackend::new();
+        l1.write(KEY, aged("local", 600));
+        let cache = TieredCache::new(&l1, MemoryBackend::new(), "schedule");
+        assert_eq!(block_on(cache.get("2025-01", TTL)).unwrap().value, "local");
+    }
+
+    #[test]
+    fn put_writes_both_levels_under_the_namespace() {
+        let l1 = MemoryBackend::new();
+        let cache = TieredCache::new(&l1, MemoryBackend::new(), "schedule");
+        block_on(cache.put("2025-01", "fresh".to_string(), TTL));
+
+        assert_eq!(l1.read(KEY).unwrap().value, "fresh");
+        assert_eq!(cache.l2.read(KEY).unwrap().value, "fresh");
+        assert!(block_on(cache.get("2025-02", TTL)).is_none());
+    }
+
+    #[test]
+    fn freshness_boundaries() {
+        let ttl = TTL as i64;
+        let revalidate_end = ttl + STALE_WHILE_REVALIDATE as i64;
+        let stale_if_error_end = ttl + STALE_IF_ERROR as i64;
+
+        assert_eq!(freshness_at(0), Freshness::Fresh);
+        assert_eq!(freshness_at(ttl - 1), Freshness::Fresh);
+        assert_eq!(freshness_at(ttl), Freshness::Revalidate);
+        assert_eq!(freshness_at(revalidate_end - 1), Freshness::Revalidate);
+        assert_eq!(freshness_at(revalidate_end), Freshness::StaleIfError);
+        assert_eq!(
+            freshness_at(stale_if_error_end - 1),
+            Freshness::StaleIfError
+        );
+        assert_eq!(freshness_at(stale_if_error_end), Freshness::Expired);
+    }
+}
@@ -0,0 +1,291 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use worker::*;
+
+use crate::{error_response, is_valid_ym, query_param, snapshots, MonthShifts, Person, ShiftCell};
+
+const MAX_CHANGE_SETS_PER_MONTH: usize = 50;
+
+#[derive(Serialize, Clone, Default)]
+pub struct ScheduleDiff {
+    #[serde(rename = "peopleAdded")]
+    pub people_added: Vec<Person>,
+    #[serde(rename = "peopleRemoved")]
+    pub people_removed: Vec<Person>,
+    pub cells: Vec<CellChange>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct CellChange {
+    #[serde(rename = "personId")]
+    pub person_id: String,
+    #[serde(rename = "personName")]
+    pub person_name: String,
+    pub day: usize,
+    pub kind: CellChangeKind,
+    pub before: Vec<ShiftCell>,
+    pub after: Vec<ShiftCell>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CellChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ScheduleChangeSet {
+    #[serde(rename = "detectedAt")]
+    pub detected_at: String,
+    #[serde(flatten)]
+    pub diff: ScheduleDiff,
+}
+
+#[derive(Serialize)]
+struct ChangesResponse {
+    ym: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    since: Option<String>,
+    #[serde(rename = "trackingSince", skip_serializing_if = "Option::is_none")]
+    tracking_since: Option<String>,
+    changes: Vec<ScheduleChangeSet>,
+}
+
+impl ScheduleDiff {
+    pub fn is_empty(&self) -> bool {
+        self.people_added.is_empty() && self.people_removed.is_empty() && self.cells.is_empty()
+    }
+}
+
+/// Compare two versions of the same month, cell by cell
+pub fn diff_month_shifts(previous: &MonthShifts, current: &MonthShifts) -> ScheduleDiff {
+    let previous_index: HashMap<&str, usize> = previous
+        .people
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (p.id.as_str(), i))
+        .collect();
+    let current_index: HashMap<&str, usize> = current
+        .people
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (p.id.as_str(), i))
+        .collect();
+
+    let mut diff = ScheduleDiff::default();
+
+    for person in &current.people {
+        if !previous_index.contains_key(person.id.as_str()) {
+            diff.people_added.push(person.clone());
+        }
+    }
+    for person in &previous.people {
+        if !current_index.contains_key(person.id.as_str()) {
+            diff.people_removed.push(person.clone());
+        }
+    }
+
+    // Walk every person seen in either version; a missing row counts as empty days
+    let mut person_ids: Vec<&Person> = current.people.iter().collect();
+    person_ids.extend(people_only_in_previous(previous, &current_index));
+    person_ids.sort_by(|a, b| a.id.cmp(&b.id));
+
+    for person in person_ids {
+        let before_row = previous_index
+            .get(person.id.as_str())
+            .and_then(|&i| previous.rows.get(i));
+        let after_row = current_index
+            .get(person.id.as_str())
+            .and_then(|&i| current.rows.get(i));
+
+        let days = before_row
+            .map_or(0, |row| row.len())
+            .max(after_row.map_or(0, |row| row.len()));
+
+        for day_idx in 0..days {
+            let before = cell_at(before_row, day_idx);
+            let after = cell_at(after_row, day_idx);
+            if before == after {
+                continue;
+            }
+
+            let kind = match (before.is_empty(), after.is_empty()) {
+                (true, false) => CellChangeKind::Added,
+                (false, true) => CellChangeKind::Removed,
+                _ => CellChangeKind::Changed,
+            };
+
+            diff.cells.push(CellChange {
+                person_id: person.id.clone(),
+                person_name: person.label().to_string(),
+                day: day_idx + 1,
+                kind,
+                before,
+                after,
+            });
+        }
+    }
+
+    diff
+}
+
+fn people_only_in_previous<'a>(
+    previous: &'a MonthShifts,
+    current_index: &HashMap<&str, usize>,
+) -> Vec<&'a Person> {
+    previous
+        .people
+        .iter()
+        .filter(|person| !current_index.contains_key(person.id.as_str()))
+        .collect()
+}
+
+/// A cell's shifts in a stable order, so upstream reordering alone is not a change
+fn cell_at(row: Option<&Vec<Option<Vec<ShiftCell>>>>, day_idx: usize) -> Vec<ShiftCell> {
+    let mut cell = row
+        .and_then(|row| row.get(day_idx))
+        .and_then(|cell| cell.clone())
+        .unwrap_or_default();
+    cell.sort_by(|a, b| cell_sort_key(a).cmp(&cell_sort_key(b)));
+    cell
+}
+
+fn cell_sort_key(cell: &ShiftCell) -> (&str, Option<&str>, Option<&str>) {
+    match cell {
+        ShiftCell::Code(code) => (code, None, None),
+        ShiftCell::Timed(timed) => (&timed.code, timed.start.as_deref(), timed.end.as_deref()),
+    }
+}
+
+fn parse_since(value: &str) -> Option<DateTime<Utc>> {
+    let trimmed = value.trim();
+    if let Ok(parsed) = DateTime::parse_from_rfc3339(trimmed) {
+        return Some(parsed.with_timezone(&Utc));
+    }
+
+    NaiveDate::parse_from_str(trimmed, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|datetime| datetime.and_utc())
+}
+
+/// API endpoint: changes between the archived versions of a month, optionally after `since`
+pub async fn handle_shift_changes(req: Request, ctx: RouteContext<Context>) -> Result<Response> {
+    let url = req.url()?;
+    let ym = match query_param(&url, "ym") {
+        Some(v) => v,
+        None => return error_response("MISSING_PARAM", "Missing required parameter: ym", 400),
+    };
+    if !is_valid_ym(&ym) {
+        return error_response("INVALID_YM", "Invalid ym format. Expected YYYY-MM", 400);
+    }
+
+    let since_param = query_param(&url, "since");
+    let since = match since_param.as_deref().map(parse_since) {
+        Some(None) => {
+            return error_response(
+                "INVALID_SINCE",
+                "Invalid since value. Expected an RFC 3339 timestamp or YYYY-MM-DD",
+                400,
+            )
+        }
+        Some(Some(since)) => Some(since),
+        None => None,
+    };
+
+    let history =
+        match snapshots::load_snapshot_history(&ctx.env, &ym, since, MAX_CHANGE_SETS_PER_MONTH)
+            .await
+        {
+            Ok(Some(history)) => history,
+            Ok(None) => {
+                return error_response("CONFIG_ERROR", "Snapshot storage not configured", 500)
+            }
+            Err(e) => {
+                console_error!("Failed to load snapshots for {}: {:?}", ym, e);
+                return error_response("STORAGE_ERROR", "Failed to load snapshots", 500);
+            }
+        };
+
+    // Each archived version is compared with the one before it
+    let changes = history
+        .versions
+        .windows(2)
+        .filter(|pair| since.is_none_or(|since| pair[1].0 > since))
+        .filter_map(|pair| {
+            let diff = diff_month_shifts(&pair[0].1, &pair[1].1);
+            (!diff.is_empty()).then(|| ScheduleChangeSet {
+                detected_at: pair[1].0.to_rfc3339(),
+                diff,
+            })
+        })
+        .collect();
+    let tracking_since = history
+        .first_captured_at
+        .map(|captured_at| captured_at.to_rfc3339());
+
+    let response = ChangesResponse {
+        ym,
+        since: since.map(|since| since.to_rfc3339()),
+        tracking_since,
+        changes,
+    };
+
+    let json = serde_json::to_string(&response)?;
+    let headers = Headers::new();
+    headers.set("Content-Type", "application/json")?;
+    headers.set("Cache-Control", "no-store")?;
+    headers.set("Access-Control-Allow-Origin", "*")?;
+
+    Ok(Response::ok(json)?.with_headers(headers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn month(rows: &str) -> MonthShifts {
+        serde_json::from_str(&format!(
+            r#"{{
+              "ym": "2025-01",
+              "people": [{{ "id": "101", "name": "Giulia Bianchi" }}],
+              "rows": {},
+              "codes": [],
+              "shiftNames": {{}}
+            }}"#,
+            rows
+        ))
+        .expect("valid month")
+    }
+
+    #[test]
+    fn reordered_cell_is_not_a_change() {
+        let previous = month(
+            r#"[[[{ "code": "M", "start": "08:00", "end": "14:00" }, { "code": "RATP", "start": "14:00", "end": "20:00" }], null]]"#,
+        );
+        let current = month(
+            r#"[[[{ "code": "RATP", "start": "14:00", "end": "20:00" }, { "code": "M", "start": "08:00", "end": "14:00" }], null]]"#,
+        );
+
+        assert!(diff_month_shifts(&previous, &current).is_empty());
+    }
+
+    #[test]
+    fn changed_times_are_still_reported() {
+        let previous = month(r#"[[[{ "code": "M", "start": "08:00", "end": "14:00" }], null]]"#);
+        let current = month(
+            r#"[[[{ "code": "M", "start": "09:00", "end": "14:00" }], [{ "code": "N", "start": "20:00", "end": "08:00" }]]]"#,
+        );
+
+        let diff = diff_month_shifts(&previous, &current);
+        let kinds: Vec<(usize, CellChangeKind)> = diff
+            .cells
+            .iter()
+            .map(|change| (change.day, change.kind))
+            .collect();
+        assert!(kinds == [(1, CellChangeKind::Changed), (2, CellChangeKind::Added)]);
+    }
+}
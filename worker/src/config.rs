@@ -1,25 +1,26 @@
-use chrono::{DateTime, Utc};
-use once_cell::sync::Lazy;
+use indexmap::IndexMap;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::RwLock;
+use std::collections::{HashMap, HashSet};
 use worker::*;
 
-const CONFIG_DISPLAY: &str = "shift-display.config.json";
-const CONFIG_STYLING: &str = "shift-styling.config.json";
-const CONFIG_COLORS: &str = "shift-colors.json";
-const CONFIG_DOCTOR_NAMES: &str = "doctor-names.json";
-const CONFIG_FULL_NAME_OVERRIDES: &str = "full-name-overrides.json";
-const CONFIG_DOCTOR_PHOTOS: &str = "doctor-photos.json";
+use crate::cache::TieredCache;
+use crate::{resolve_config_cache_ttl, utils};
 
-#[derive(Clone, Debug)]
-struct CachedConfig {
-    json: String,
-    fetched_at: DateTime<Utc>,
-}
+pub const CONFIG_DISPLAY: &str = "shift-display.config.json";
+pub const CONFIG_STYLING: &str = "shift-styling.config.json";
+pub const CONFIG_COLORS: &str = "shift-colors.json";
+pub const CONFIG_DOCTOR_NAMES: &str = "doctor-names.json";
+pub const CONFIG_FULL_NAME_OVERRIDES: &str = "full-name-overrides.json";
+const CONFIG_DOCTOR_PHOTOS: &str = "doctor-photos.json";
+pub const CONFIG_COVERAGE: &str = "shift-coverage.config.json";
+pub const CONFIG_STATS: &str = "shift-stats.config.json";
+pub const CONFIG_RULES: &str = "schedule-rules.config.json";
+pub const CONFIG_GROUPS: &str = "person-groups.config.json";
+pub const CONFIG_CALENDAR: &str = "calendar-days.config.json";
 
-static CONFIG_CACHE: Lazy<RwLock<HashMap<String, CachedConfig>>> =
-    Lazy::new(|| RwLock::new(HashMap::new()));
+// Namespace for R2 config files in the tiered cache
+const CONFIG_CACHE_NAMESPACE: &str = "config";
 
 #[derive(Deserialize, Default, Serialize)]
 pub struct RawShiftDisplayConfig {
@@ -45,6 +46,213 @@ pub struct ConditionalUnderline {
     pub weekdays: Vec<u8>,
 }
 
+/// `shift-colors.json`: fixed colours per code, plus the hash-based palette for the rest
+#[derive(Deserialize, Default, Serialize)]
+pub struct ShiftColorsConfig {
+    #[serde(default)]
+    pub comment: String,
+    #[serde(default)]
+    pub colors: HashMap<String, ShiftColor>,
+    #[serde(default)]
+    pub fallback: ColorFallback,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct ShiftColor {
+    pub background: String,
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// HSL ranges for generated colours (same keys and defaults as `DEFAULT_SHIFT_COLORS`)
+#[derive(Deserialize, Serialize)]
+pub struct ColorFallback {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(default = "default_saturation_min")]
+    pub saturation_min: f64,
+    #[serde(default = "default_saturation_range")]
+    pub saturation_range: f64,
+    #[serde(default = "default_lightness_min")]
+    pub lightness_min: f64,
+    #[serde(default = "default_lightness_range")]
+    pub lightness_range: f64,
+    #[serde(default = "default_text_lightness_offset")]
+    pub text_lightness_offset: f64,
+}
+
+impl Default for ColorFallback {
+    fn default() -> Self {
+        ColorFallback {
+            comment: None,
+            saturation_min: default_saturation_min(),
+            saturation_range: default_saturation_range(),
+            lightness_min: default_lightness_min(),
+            lightness_range: default_lightness_range(),
+            text_lightness_offset: default_text_lightness_offset(),
+        }
+    }
+}
+
+fn default_saturation_min() -> f64 {
+    40.0
+}
+
+fn default_saturation_range() -> f64 {
+    30.0
+}
+
+fn default_lightness_min() -> f64 {
+    75.0
+}
+
+fn default_lightness_range() -> f64 {
+    15.0
+}
+
+fn default_text_lightness_offset() -> f64 {
+    65.0
+}
+
+/// Shift codes that must be staffed every day, for the coverage report
+#[derive(Deserialize, Default, Serialize)]
+pub struct CoverageConfig {
+    #[serde(default)]
+    pub required: Vec<String>,
+    /// Per-weekday overrides keyed "0" (Sunday) through "6" (Saturday)
+    #[serde(default)]
+    pub weekdays: HashMap<String, Vec<String>>,
+}
+
+impl CoverageConfig {
+    pub fn required_for(&self, weekday_from_sunday: u32) -> &[String] {
+        self.weekdays
+            .get(&weekday_from_sunday.to_string())
+            .unwrap_or(&self.required)
+    }
+}
+
+/// Shift classification for the workload report
+#[derive(Deserialize, Default, Serialize)]
+pub struct StatsConfig {
+    #[serde(rename = "nightCodes", default)]
+    pub night_codes: Vec<String>,
+}
+
+impl StatsConfig {
+    pub fn is_night(&self, code: &str) -> bool {
+        self.night_codes
+            .iter()
+            .any(|night| night.trim().eq_ignore_ascii_case(code.trim()))
+    }
+}
+
+/// `doctor-names.json`: API IDs, upstream names and pseudonyms mapped to display names
+#[derive(Deserialize, Default, Serialize)]
+pub struct DoctorNamesConfig {
+    #[serde(default)]
+    pub comment: String,
+    /// File order matters: the fuzzy lookups take the first match, like the frontend
+    #[serde(default)]
+    pub names: IndexMap<String, String>,
+}
+
+/// `full-name-overrides.json`: names that stay expanded in compact layouts
+#[derive(Default)]
+pub struct FullNameOverrides {
+    names: HashSet<String>,
+}
+
+impl From<Vec<String>> for FullNameOverrides {
+    fn from(raw: Vec<String>) -> Self {
+        FullNameOverrides {
+            names: raw
+                .into_iter()
+                .map(|name| name.trim().to_lowercase())
+                .filter(|name| !name.is_empty())
+                .collect(),
+        }
+    }
+}
+
+impl FullNameOverrides {
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(&name.trim().to_lowercase())
+    }
+}
+
+/// One named group; `members` are person IDs or names, in display order
+#[derive(Deserialize, Serialize, Clone)]
+pub struct GroupDefinition {
+    pub id: String,
+    #[serde(default)]
+    pub label: String,
+    #[serde(default)]
+    pub members: Vec<String>,
+}
+
+/// Named groups of people (seniors, residents, ...), in display order
+#[derive(Deserialize, Default, Serialize)]
+pub struct GroupsConfig {
+    #[serde(default)]
+    pub groups: Vec<GroupDefinition>,
+}
+
+/// A local holiday (e.g. the patron saint) or a closure day
+#[derive(Deserialize, Serialize, Clone)]
+pub struct ExtraDay {
+    /// `YYYY-MM-DD` for a single day, or `MM-DD` for every year
+    pub date: String,
+    pub name: String,
+    /// A closure is flagged separately and does not count as a holiday
+    #[serde(default)]
+    pub closure: bool,
+}
+
+/// Local additions to the built-in Italian calendar
+#[derive(Deserialize, Default, Serialize)]
+pub struct CalendarConfig {
+    #[serde(default)]
+    pub days: Vec<ExtraDay>,
+}
+
+/// Working-time thresholds for the violations report. A limit of 0 turns that rule off.
+#[derive(Deserialize, Serialize)]
+pub struct RulesConfig {
+    #[serde(rename = "minRestHours", default = "default_min_rest_hours")]
+    pub min_rest_hours: f64,
+    #[serde(
+        rename = "maxConsecutiveNights",
+        default = "default_max_consecutive_nights"
+    )]
+    pub max_consecutive_nights: u32,
+    #[serde(rename = "maxWeeklyHours", default = "default_max_weekly_hours")]
+    pub max_weekly_hours: f64,
+}
+
+fn default_min_rest_hours() -> f64 {
+    11.0
+}
+
+fn default_max_consecutive_nights() -> u32 {
+    3
+}
+
+fn default_max_weekly_hours() -> f64 {
+    48.0
+}
+
+impl Default for RulesConfig {
+    fn default() -> Self {
+        RulesConfig {
+            min_rest_hours: default_min_rest_hours(),
+            max_consecutive_nights: default_max_consecutive_nights(),
+            max_weekly_hours: default_max_weekly_hours(),
+        }
+    }
+}
+
 pub struct ShiftDisplayConfig {
     pub alias_map: HashMap<String, String>,
     pub label_map: HashMap<String, String>,
@@ -112,6 +320,21 @@ impl ShiftDisplayConfig {
         trimmed.to_string()
     }
 
+    /// The code the grid shows and colours, as `getShiftDisplayCode` in `src/lib/shift-format.ts`:
+    /// the leading identifier without any parenthetical, then aliased
+    pub fn display_code(&self, code: &str) -> String {
+        let normalised = code
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .replace(['–', '—'], "-");
+        let without_paren = normalised.split('(').next().unwrap_or_default().trim();
+        match without_paren.split(' ').next() {
+            Some(leading) if !leading.is_empty() => self.normalize_token(leading),
+            _ => self.normalize_token(without_paren),
+        }
+    }
+
     pub fn label_override(&self, key: &str) -> Option<String> {
         if key.trim().is_empty() {
             return None;
@@ -149,38 +372,33 @@ impl ShiftDisplayConfig {
 
 /// Fetch config from R2 with caching
 async fn fetch_config_from_r2(
+    env: &Env,
     bucket: &Bucket,
     config_key: &str,
     cache_ttl_seconds: u64,
 ) -> Result<String> {
-    // Check cache first
-    {
-        let cache = CONFIG_CACHE
-            .read()
-            .map_err(|e| Error::RustError(format!("Failed to acquire cache read lock: {}", e)))?;
+    let cache = TieredCache::from_env(env, CONFIG_CACHE_NAMESPACE);
 
-        if let Some(cached) = cache.get(config_key) {
-            let age_seconds = Utc::now()
-                .signed_duration_since(cached.fetched_at)
-                .num_seconds();
-
-            if age_seconds >= 0 && (age_seconds as u64) < cache_ttl_seconds {
-                console_log!(
-                    "Config cache hit for {}: {} seconds old",
-                    config_key,
-                    age_seconds
-                );
-                return Ok(cached.json.clone());
-            } else {
-                console_log!(
-                    "Config cache miss for {}: {} seconds old (stale)",
-                    config_key,
-                    age_seconds
-                );
-            }
+    // Check cache first
+    if let Some(cached) = cache.get(config_key, cache_ttl_seconds).await {
+        let age_seconds = cached.age_seconds();
+
+        if age_seconds >= 0 && (age_seconds as u64) < cache_ttl_seconds {
+            console_log!(
+                "Config cache hit for {}: {} seconds old",
+                config_key,
+                age_seconds
+            );
+            return Ok(cached.value);
         } else {
-            console_log!("Config cache miss for {}: not found", config_key);
+            console_log!(
+                "Config cache miss for {}: {} seconds old (stale)",
+                config_key,
+                age_seconds
+            );
         }
+    } else {
+        console_log!("Config cache miss for {}: not found", config_key);
     }
 
     // Fetch from R2
@@ -213,63 +431,47 @@ async fn fetch_config_from_r2(
         .map_err(|e| Error::RustError(format!("Invalid JSON in config {}: {}", config_key, e)))?;
 
     // Update cache
-    {
-        let mut cache = CONFIG_CACHE
-            .write()
-            .map_err(|e| Error::RustError(format!("Failed to acquire cache write lock: {}", e)))?;
-
-        cache.insert(
-            config_key.to_string(),
-            CachedConfig {
-                json: json_str.clone(),
-                fetched_at: Utc::now(),
-            },
-        );
-    }
+    cache
+        .put(config_key, json_str.clone(), cache_ttl_seconds)
+        .await;
 
     Ok(json_str)
 }
 
-/// Get shift display config from R2
-pub async fn get_shift_display_config(
-    bucket: &Bucket,
-    cache_ttl_seconds: u64,
-) -> Result<ShiftDisplayConfig> {
-    let json_str = fetch_config_from_r2(bucket, CONFIG_DISPLAY, cache_ttl_seconds).await?;
-
-    let raw: RawShiftDisplayConfig = serde_json::from_str(&json_str).unwrap_or_else(|e| {
-        console_log!(
-            "Failed to parse shift display config: {:?}, using defaults",
-            e
-        );
-        RawShiftDisplayConfig::default()
-    });
-
-    Ok(ShiftDisplayConfig::from(raw))
-}
-
-/// Get shift styling config from R2
-pub async fn get_shift_styling_config(
-    bucket: &Bucket,
-    cache_ttl_seconds: u64,
-) -> Result<ShiftStylingConfig> {
-    let json_str = fetch_config_from_r2(bucket, CONFIG_STYLING, cache_ttl_seconds).await?;
+/// Load a JSON config file from R2 through the config cache (keyed by filename),
+/// falling back to `T::default()` when the bucket, the file or its contents are unusable
+pub async fn load_json_config<T: DeserializeOwned + Default>(env: &Env, config_key: &str) -> T {
+    let bucket = match env.bucket("CONFIG_BUCKET") {
+        Ok(bucket) => bucket,
+        Err(e) => {
+            console_log!("CONFIG_BUCKET not configured: {:?}, using defaults", e);
+            return T::default();
+        }
+    };
 
-    let config: ShiftStylingConfig = serde_json::from_str(&json_str).unwrap_or_else(|e| {
-        console_log!(
-            "Failed to parse shift styling config: {:?}, using defaults",
-            e
-        );
-        ShiftStylingConfig::default()
-    });
+    let json_str =
+        match fetch_config_from_r2(env, &bucket, config_key, resolve_config_cache_ttl(env)).await {
+            Ok(json_str) => json_str,
+            Err(e) => {
+                console_log!(
+                    "Failed to fetch {} from R2: {:?}, using defaults",
+                    config_key,
+                    e
+                );
+                return T::default();
+            }
+        };
 
-    Ok(config)
+    serde_json::from_str(&json_str).unwrap_or_else(|e| {
+        console_log!("Failed to parse {}: {:?}, using defaults", config_key, e);
+        T::default()
+    })
 }
 
 /// API endpoint: Get config by name
 pub async fn handle_get_config(
-    _req: Request,
-    ctx: RouteContext<()>,
+    req: Request,
+    ctx: RouteContext<Context>,
     config_name: String,
 ) -> Result<Response> {
     let bucket = ctx.bucket("CONFIG_BUCKET")?;
@@ -287,20 +489,34 @@ pub async fn handle_get_config(
         "doctor-names" => CONFIG_DOCTOR_NAMES,
         "full-name-overrides" => CONFIG_FULL_NAME_OVERRIDES,
         "doctor-photos" => CONFIG_DOCTOR_PHOTOS,
+        "shift-coverage" => CONFIG_COVERAGE,
+        "shift-stats" => CONFIG_STATS,
+        "schedule-rules" => CONFIG_RULES,
+        "person-groups" => CONFIG_GROUPS,
+        "calendar-days" => CONFIG_CALENDAR,
         _ => {
             return Response::error("Invalid config name", 400);
         }
     };
 
-    match fetch_config_from_r2(&bucket, config_key, cache_ttl_seconds).await {
+    match fetch_config_from_r2(&ctx.env, &bucket, config_key, cache_ttl_seconds).await {
         Ok(json_str) => {
+            let etag = utils::strong_etag(json_str.as_bytes());
+
             let headers = Headers::new();
             headers.set("Content-Type", "application/json")?;
             headers.set(
                 "Cache-Control",
                 &format!("public, max-age={}", cache_ttl_seconds),
             )?;
+            headers.set("ETag", &etag)?;
             headers.set("Access-Control-Allow-Origin", "*")?;
+            headers.set("Access-Control-Expose-Headers", "ETag")?;
+
+            let if_none_match = req.headers().get("If-None-Match")?;
+            if if_none_match.is_some_and(|header| utils::etag_matches(&header, &etag)) {
+                return Ok(Response::empty()?.with_status(304).with_headers(headers));
+            }
 
             Ok(Response::ok(json_str)?.with_headers(headers))
         }
@@ -311,10 +527,8 @@ pub async fn handle_get_config(
     }
 }
 
-/// Clear config cache (useful for testing/admin)
-pub fn clear_config_cache() {
-    if let Ok(mut cache) = CONFIG_CACHE.write() {
-        cache.clear();
-        console_log!("Config cache cleared");
-    }
+/// Clear this isolate's config cache (useful for testing/admin)
+pub fn clear_config_cache(env: &Env) {
+    TieredCache::from_env(env, CONFIG_CACHE_NAMESPACE).clear_local();
+    console_log!("Config cache cleared");
 }
@@ -0,0 +1,205 @@
+use chrono::{Datelike, NaiveDate};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use worker::*;
+
+use crate::config::{self, CoverageConfig};
+use crate::{
+    build_success_response, error_response, get_config, get_days_in_month, is_valid_ym,
+    load_month_shifts, query_param, response_ttl, MonthShifts, ScheduleVersion,
+};
+
+/// How many people hold each code on one day, and which required codes nobody holds
+#[derive(Serialize)]
+struct DayCoverage {
+    date: String,
+    counts: BTreeMap<String, usize>,
+    #[serde(rename = "peopleOnShift")]
+    people_on_shift: usize,
+    required: Vec<String>,
+    unstaffed: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct CoverageReport {
+    ym: String,
+    codes: Vec<String>,
+    days: Vec<DayCoverage>,
+    #[serde(rename = "daysWithGaps")]
+    days_with_gaps: usize,
+}
+
+/// API endpoint: per-day staffing counts for a month
+pub async fn handle_shift_coverage(req: Request, ctx: RouteContext<Context>) -> Result<Response> {
+    let config = match get_config(&ctx.env) {
+        Ok(cfg) => cfg,
+        Err(e) => return error_response("CONFIG_ERROR", &e.to_string(), 500),
+    };
+
+    let url = req.url()?;
+    let ym = match query_param(&url, "ym") {
+        Some(v) => v,
+        None => return error_response("MISSING_PARAM", "Missing required parameter: ym", 400),
+    };
+    if !is_valid_ym(&ym) {
+        return error_response("INVALID_YM", "Invalid ym format. Expected YYYY-MM", 400);
+    }
+
+    // `required=M,P,N` overrides the R2 config for ad-hoc checks
+    let coverage_config = match query_param(&url, "required") {
+        Some(list) => CoverageConfig {
+            required: list
+                .split(',')
+                .map(|code| code.trim().to_string())
+                .filter(|code| !code.is_empty())
+                .collect(),
+            ..CoverageConfig::default()
+        },
+        None => config::load_json_config(&ctx.env, config::CONFIG_COVERAGE).await,
+    };
+
+    let (month_shifts, cache_status) =
+        match load_month_shifts(&ctx, &config, &ym, ScheduleVersion::Live).await {
+            Ok(result) => result,
+            Err(failure) => return failure.into_response(),
+        };
+
+    let report = build_coverage_report(&month_shifts, &coverage_config);
+    let json = serde_json::to_string(&report)?;
+    let if_none_match = req.headers().get("If-None-Match")?;
+    build_success_response(
+        json,
+        response_ttl(&config, cache_status),
+        cache_status,
+        if_none_match.as_deref(),
+    )
+}
+
+fn build_coverage_report(
+    month_shifts: &MonthShifts,
+    coverage_config: &CoverageConfig,
+) -> CoverageReport {
+    let first_day = NaiveDate::parse_from_str(&format!("{}-01", month_shifts.ym), "%Y-%m-%d").ok();
+    // A month nobody works still reports every day as a gap
+    let days_in_month = get_days_in_month(&month_shifts.ym);
+
+    let mut codes: BTreeSet<String> = month_shifts.codes.iter().cloned().collect();
+    let mut days = Vec::with_capacity(days_in_month);
+
+    for day_index in 0..days_in_month {
+        let date =
+            first_day.and_then(|first| first.checked_add_days(chrono::Days::new(day_index as u64)));
+
+        // Count people, not cells: two shifts with the same code count once
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        let mut people_on_shift = 0;
+        for row in &month_shifts.rows {
+            let Some(Some(cells)) = row.get(day_index) else {
+                continue;
+            };
+            people_on_shift += 1;
+            let person_codes: BTreeSet<&str> = cells.iter().map(|cell| cell.code()).collect();
+            for code in person_codes {
+                *counts.entry(code.to_string()).or_insert(0) += 1;
+            }
+        }
+
+        let required: Vec<String> = date
+            .map(|date| coverage_config.required_for(date.weekday().num_days_from_sunday()))
+            .unwrap_or(coverage_config.required.as_slice())
+            .to_vec();
+        let unstaffed: Vec<String> = required
+            .iter()
+            .filter(|code| !counts.contains_key(code.as_str()))
+            .cloned()
+            .collect();
+        codes.extend(required.iter().cloned());
+
+        days.push(DayCoverage {
+            date: date
+                .map(|date| date.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            counts,
+            people_on_shift,
+            required,
+            unstaffed,
+        });
+    }
+
+    let days_with_gaps = days.iter().filter(|day| !day.unstaffed.is_empty()).count();
+
+    CoverageReport {
+        ym: month_shifts.ym.clone(),
+        codes: codes.into_iter().collect(),
+        days,
+        days_with_gaps,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn coverage_config() -> CoverageConfig {
+        serde_json::from_value(json!({ "required": ["M", "N"], "weekdays": { "0": ["N"] } }))
+            .unwrap()
+    }
+
+    fn february(rows: serde_json::Value) -> MonthShifts {
+        serde_json::from_value(json!({
+            "ym": "2025-02",
+            "people": [
+                { "id": "101", "name": "Giulia Bianchi" },
+                { "id": "102", "name": "Marco Rossi" }
+            ],
+            "rows": rows,
+            "codes": ["M", "N"],
+            "shiftNames": {}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn counts_people_per_code_and_reports_gaps() {
+        // 2025-02-01 is a Saturday, 2025-02-02 a Sunday
+        let month = february(json!([
+            [["M", { "code": "M", "start": "14:00", "end": "20:00" }], ["N"]],
+            [["N"]]
+        ]));
+
+        let report = build_coverage_report(&month, &coverage_config());
+
+        assert_eq!(report.days.len(), 28);
+        let saturday = &report.days[0];
+        assert_eq!(saturday.date, "2025-02-01");
+        assert_eq!(saturday.people_on_shift, 2);
+        // Two M shifts for one person count once
+        assert_eq!(saturday.counts.get("M"), Some(&1));
+        assert_eq!(saturday.counts.get("N"), Some(&1));
+        assert!(saturday.unstaffed.is_empty());
+
+        // Sunday only needs a night
+        let sunday = &report.days[1];
+        assert_eq!(sunday.required, ["N"]);
+        assert!(sunday.unstaffed.is_empty());
+
+        let monday = &report.days[2];
+        assert_eq!(monday.people_on_shift, 0);
+        assert_eq!(monday.unstaffed, ["M", "N"]);
+        assert_eq!(report.days_with_gaps, 26);
+    }
+
+    #[test]
+    fn empty_month_reports_every_day_and_required_codes() {
+        let mut month = february(json!([]));
+        month.people.clear();
+        month.codes.clear();
+
+        let report = build_coverage_report(&month, &coverage_config());
+
+        assert_eq!(report.days.len(), 28);
+        assert_eq!(report.days_with_gaps, 28);
+        assert_eq!(report.codes, ["M", "N"]);
+    }
+}
@@ -0,0 +1,254 @@
+use worker::*;
+
+use crate::xlsx::{self, Sheet};
+use crate::{
+    build_file_response, check_version_access, error_response, get_config, groups, holidays,
+    is_valid_ym, load_month_shifts, query_param, response_ttl, restrict_to_version_audience,
+    unknown_group_response, MonthShifts, ScheduleVersion, ShiftCell,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+enum ExportFormat {
+    Csv,
+    Xlsx,
+}
+
+impl ExportFormat {
+    fn from_query(value: Option<&str>) -> Option<Self> {
+        match value.map(str::trim) {
+            None | Some("") | Some("csv") => Some(ExportFormat::Csv),
+            Some("xlsx") => Some(ExportFormat::Xlsx),
+            _ => None,
+        }
+    }
+}
+
+/// What each grid cell shows
+#[derive(Clone, Copy, PartialEq)]
+enum CellContent {
+    Codes,
+    Labels,
+}
+
+impl CellContent {
+    fn from_query(value: Option<&str>) -> Option<Self> {
+        match value.map(str::trim) {
+            None | Some("") | Some("codes") => Some(CellContent::Codes),
+            Some("labels") => Some(CellContent::Labels),
+            _ => None,
+        }
+    }
+}
+
+/// API endpoint: the month grid as a CSV or XLSX download
+pub async fn handle_export(req: Request, ctx: RouteContext<Context>) -> Result<Response> {
+    let config = match get_config(&ctx.env) {
+        Ok(cfg) => cfg,
+        Err(e) => return error_response("CONFIG_ERROR", &e.to_string(), 500),
+    };
+
+    let url = req.url()?;
+    let ym = match query_param(&url, "ym") {
+        Some(v) => v,
+        None => return error_response("MISSING_PARAM", "Missing required parameter: ym", 400),
+    };
+    if !is_valid_ym(&ym) {
+        return error_response("INVALID_YM", "Invalid ym format. Expected YYYY-MM", 400);
+    }
+
+    let Some(format) = ExportFormat::from_query(query_param(&url, "format").as_deref()) else {
+        return error_response(
+            "INVALID_FORMAT",
+            "Invalid format value. Expected csv or xlsx",
+            400,
+        );
+    };
+    let Some(content) = CellContent::from_query(query_param(&url, "cells").as_deref()) else {
+        return error_response(
+            "INVALID_CELLS",
+            "Invalid cells value. Expected codes or labels",
+            400,
+        );
+    };
+
+    let version = match ScheduleVersion::from_query(query_param(&url, "version").as_deref()) {
+        Some(version) => version,
+        None => {
+            return error_response(
+                "INVALID_VERSION",
+                "Invalid version value. Expected live, draft or published",
+                400,
+            )
+        }
+    };
+    if let Some(denied) = check_version_access(&req, &ctx, version) {
+        return denied;
+    }
+
+    let (mut month_shifts, cache_status) =
+        match load_month_shifts(&ctx, &config, &ym, version).await {
+            Ok(result) => result,
+            Err(failure) => return failure.into_response(),
+        };
+
+    let group = query_param(&url, "group")
+        .map(|group| group.trim().to_string())
+        .filter(|group| !group.is_empty());
+    if let Some(group) = &group {
+        if !groups::retain_group(&mut month_shifts, group) {
+            return unknown_group_response(group);
+        }
+    }
+    // Months cached before `days` existed fall back to the built-in calendar
+    if month_shifts.days.is_empty() {
+        month_shifts.days = holidays::HolidayCalendar::default().month_days(&ym);
+    }
+
+    let grid = grid_rows(&month_shifts, content);
+    let (body, content_type) = match format {
+        ExportFormat::Csv => (render_csv(&grid), "text/csv; charset=utf-8"),
+        ExportFormat::Xlsx => (
+            xlsx::write_workbook(&[
+                Sheet {
+                    name: "Turni".to_string(),
+                    rows: grid,
+                    frozen_rows: 1,
+                    frozen_columns: 1,
+                },
+                Sheet {
+                    name: "Legenda".to_string(),
+                    rows: legend_rows(&month_shifts),
+                    frozen_rows: 1,
+                    frozen_columns: 0,
+                },
+            ]),
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        ),
+    };
+
+    let filename = match (&group, format) {
+        (Some(group), ExportFormat::Csv) => format!("turni-{}-{}.csv", ym, group),
+        (Some(group), ExportFormat::Xlsx) => format!("turni-{}-{}.xlsx", ym, group),
+        (None, ExportFormat::Csv) => format!("turni-{}.csv", ym),
+        (None, ExportFormat::Xlsx) => format!("turni-{}.xlsx", ym),
+    };
+
+    let if_none_match = req.headers().get("If-None-Match")?;
+    let response = build_file_response(
+        body,
+        content_type,
+        &format!(
+            "attachment; filename=\"{}\"",
+            filename.replace(['"', '\\'], "_")
+        ),
+        response_ttl(&config, cache_status),
+        cache_status,
+        if_none_match.as_deref(),
+    )?;
+    restrict_to_version_audience(response, version, &req)
+}
+
+/// Header row ("Nome", then "Mer 01" per day) followed by one row per person
+fn grid_rows(month_shifts: &MonthShifts, content: CellContent) -> Vec<Vec<String>> {
+    let mut header = vec!["Nome".to_string()];
+    header.extend(month_shifts.days.iter().map(holidays::DayInfo::short_label));
+
+    let mut rows = vec![header];
+    for (person, row) in month_shifts.people.iter().zip(&month_shifts.rows) {
+        let mut line = vec![person.label().to_string()];
+        line.extend(row.iter().map(|cell| {
+            cell.iter()
+                .flatten()
+                .map(|entry| cell_text(entry, content, month_shifts))
+                .collect::<Vec<_>>()
+                .join(", ")
+        }));
+        rows.push(line);
+    }
+    rows
+}
+
+fn cell_text(entry: &ShiftCell, content: CellContent, month_shifts: &MonthShifts) -> String {
+    let code = entry.code();
+    match content {
+        CellContent::Codes => code.to_string(),
+        CellContent::Labels => month_shifts
+            .shift_names
+            .get(code)
+            .cloned()
+            .unwrap_or_else(|| code.to_string()),
+    }
+}
+
+/// Codes used this month and their resolved names
+fn legend_rows(month_shifts: &MonthShifts) -> Vec<Vec<String>> {
+    let mut rows = vec![vec!["Codice".to_string(), "Turno".to_string()]];
+    rows.extend(month_shifts.codes.iter().map(|code| {
+        vec![
+            code.clone(),
+            month_shifts
+                .shift_names
+                .get(code)
+                .cloned()
+                .unwrap_or_default(),
+        ]
+    }));
+    rows
+}
+
+/// RFC 4180 CSV with a UTF-8 BOM, so spreadsheet apps read accented names correctly
+fn render_csv(rows: &[Vec<String>]) -> Vec<u8> {
+    let mut csv = String::from("\u{feff}");
+    for row in rows {
+        let line: Vec<String> = row.iter().map(|value| csv_field(value)).collect();
+        csv.push_str(&line.join(","));
+        csv.push_str("\r\n");
+    }
+    csv.into_bytes()
+}
+
+/// Quote per RFC 4180. Values a spreadsheet would read as a formula get a leading `'`,
+/// so a name like `=HYPERLINK(...)` coming from upstream stays plain text.
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_field_quotes_separators_and_quotes() {
+        assert_eq!(csv_field("Rossi"), "Rossi");
+        assert_eq!(csv_field("Rossi, Mario"), "\"Rossi, Mario\"");
+        assert_eq!(csv_field("Dr. \"Mario\""), "\"Dr. \"\"Mario\"\"\"");
+        assert_eq!(csv_field("a\nb"), "\"a\nb\"");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[test]
+    fn csv_field_neutralises_formula_prefixes() {
+        assert_eq!(csv_field("=1+1"), "'=1+1");
+        assert_eq!(csv_field("+39 333"), "'+39 333");
+        assert_eq!(csv_field("-2"), "'-2");
+        assert_eq!(csv_field("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(csv_field("\tM"), "'\tM");
+        assert_eq!(csv_field("\rM"), "\"'\rM\"");
+        assert_eq!(
+            csv_field("=HYPERLINK(\"x\",\"y\")"),
+            "\"'=HYPERLINK(\"\"x\"\",\"\"y\"\")\""
+        );
+        // Only the first character matters
+        assert_eq!(csv_field("M-N"), "M-N");
+    }
+}
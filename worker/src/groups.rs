@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::config::GroupsConfig;
+use crate::{MonthShifts, Person};
+
+/// A configured group and the people in it, in display order
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PersonGroup {
+    pub id: String,
+    pub label: String,
+    /// Person IDs present in the schedule
+    pub members: Vec<String>,
+}
+
+/// Put each person in the first group that lists their ID or name, then order
+/// `people` by group and position within it. Returns every configured group.
+pub fn assign_groups(people: &mut [Person], config: &GroupsConfig) -> Vec<PersonGroup> {
+    let mut positions: HashMap<String, (usize, usize)> = HashMap::new();
+    for person in people.iter_mut() {
+        let found = config
+            .groups
+            .iter()
+            .enumerate()
+            .find_map(|(group_idx, group)| {
+                let member_idx = group
+                    .members
+                    .iter()
+                    .position(|member| member_matches(member, person))?;
+                Some((group_idx, member_idx))
+            });
+        person.group = found.map(|(group_idx, member_idx)| {
+            positions.insert(person.id.clone(), (group_idx, member_idx));
+            config.groups[group_idx].id.clone()
+        });
+    }
+    sort_by_position(people, &positions);
+
+    config
+        .groups
+        .iter()
+        .map(|group| PersonGroup {
+            id: group.id.clone(),
+            label: if group.label.trim().is_empty() {
+                group.id.clone()
+            } else {
+                group.label.clone()
+            },
+            members: people
+                .iter()
+                .filter(|person| person.group.as_deref() == Some(group.id.as_str()))
+                .map(|person| person.id.clone())
+                .collect(),
+        })
+        .collect()
+}
+
+/// Union of the groups of several months, keeping the first month's order
+pub fn merge_groups<'a>(months: impl IntoIterator<Item = &'a MonthShifts>) -> Vec<PersonGroup> {
+    let mut merged: Vec<PersonGroup> = Vec::new();
+    for month_shifts in months {
+        for group in &month_shifts.groups {
+            let idx = match merged.iter().position(|existing| existing.id == group.id) {
+                Some(idx) => idx,
+                None => {
+                    merged.push(PersonGroup {
+                        members: Vec::new(),
+                        ..group.clone()
+                    });
+                    merged.len() - 1
+                }
+            };
+            for member in &group.members {
+                if !merged[idx].members.contains(member) {
+                    merged[idx].members.push(member.clone());
+                }
+            }
+        }
+    }
+    merged
+}
+
+/// Order `people` to match `groups`; everyone else follows, by name
+pub fn sort_people(people: &mut [Person], groups: &[PersonGroup]) {
+    let mut positions: HashMap<String, (usize, usize)> = HashMap::new();
+    for (group_idx, group) in groups.iter().enumerate() {
+        for (member_idx, member) in group.members.iter().enumerate() {
+            positions
+                .entry(member.clone())
+                .or_insert((group_idx, member_idx));
+        }
+    }
+    sort_by_position(people, &positions);
+}
+
+/// Keep only the rows of people in `group_id`.
+/// Returns false when the month has no such group.
+pub fn retain_group(month_shifts: &mut MonthShifts, group_id: &str) -> bool {
+    if !month_shifts.groups.iter().any(|group| group.id == group_id) {
+        return false;
+    }
+
+    let keep: Vec<bool> = month_shifts
+        .people
+        .iter()
+        .map(|person| person.group.as_deref() == Some(group_id))
+        .collect();
+    retain_aligned(&mut month_shifts.people, &keep);
+    retain_aligned(&mut month_shifts.rows, &keep);
+    retain_aligned(&mut month_shifts.carry_in, &keep);
+    if month_shifts.carry_in.iter().all(Option::is_none) {
+        month_shifts.carry_in.clear();
+    }
+    month_shifts.groups.retain(|group| group.id == group_id);
+    true
+}
+
+/// Keep the entries whose flag is set; an empty `items` (e.g. no carry-in) stays empty
+fn retain_aligned<T>(items: &mut Vec<T>, keep: &[bool]) {
+    let mut flags = keep.iter();
+    items.retain(|_| flags.next().copied().unwrap_or(false));
+}
+
+fn member_matches(member: &str, person: &Person) -> bool {
+    let member = member.trim();
+    if member.is_empty() {
+        return false;
+    }
+    member == person.id
+        || [&person.resolved_name, &person.display_name, &person.name]
+            .iter()
+            .any(|name| name.trim().eq_ignore_ascii_case(member))
+}
+
+fn sort_by_position(people: &mut [Person], positions: &HashMap<String, (usize, usize)>) {
+    people.sort_by_cached_key(|person| {
+        (
+            positions
+                .get(&person.id)
+                .copied()
+                .unwrap_or((usize::MAX, usize::MAX)),
+            person.label().to_lowercase(),
+            person.id.clone(),
+        )
+    });
+}
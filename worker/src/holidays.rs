@@ -0,0 +1,268 @@
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use worker::*;
+
+use crate::config::{self, CalendarConfig, ExtraDay};
+
+// Fixed-date Italian national holidays (month, day, name)
+const FIXED_HOLIDAYS: &[(u32, u32, &str)] = &[
+    (1, 1, "Capodanno"),
+    (1, 6, "Epifania"),
+    (4, 25, "Festa della Liberazione"),
+    (5, 1, "Festa del Lavoro"),
+    (6, 2, "Festa della Repubblica"),
+    (8, 15, "Ferragosto"),
+    (11, 1, "Ognissanti"),
+    (12, 8, "Immacolata Concezione"),
+    (12, 25, "Natale"),
+    (12, 26, "Santo Stefano"),
+];
+
+// Italian weekday abbreviations, indexed like `DayInfo::weekday` (0=Sunday)
+const WEEKDAY_LABELS: [&str; 7] = ["Dom", "Lun", "Mar", "Mer", "Gio", "Ven", "Sab"];
+
+// San Francesco d'Assisi became a national holiday again from 2026
+const SAN_FRANCESCO_FROM_YEAR: i32 = 2026;
+
+/// Name of the Italian national holiday falling on `date`, if any
+pub fn italian_holiday(date: NaiveDate) -> Option<&'static str> {
+    if let Some((_, _, name)) = FIXED_HOLIDAYS
+        .iter()
+        .find(|(month, day, _)| date.month() == *month && date.day() == *day)
+    {
+        return Some(name);
+    }
+
+    if date.month() == 10 && date.day() == 4 && date.year() >= SAN_FRANCESCO_FROM_YEAR {
+        return Some("San Francesco d'Assisi");
+    }
+
+    let easter = easter_sunday(date.year())?;
+    if date == easter {
+        return Some("Pasqua");
+    }
+    if Some(date) == easter.succ_opt() {
+        return Some("Lunedì dell'Angelo");
+    }
+
+    None
+}
+
+/// Per-day calendar metadata carried in the `days` array of the shifts response
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DayInfo {
+    pub date: String,
+    /// 0=Sunday through 6=Saturday, as in `shift-styling.config.json`
+    pub weekday: u32,
+    /// ISO 8601 week, e.g. `2025-W41`
+    #[serde(rename = "isoWeek")]
+    pub iso_week: String,
+    pub holiday: bool,
+    #[serde(rename = "holidayName", skip_serializing_if = "Option::is_none")]
+    pub holiday_name: Option<String>,
+    /// Name of a configured closure on this day
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub closure: Option<String>,
+}
+
+impl DayInfo {
+    /// Column heading such as `Mer 01`
+    pub fn short_label(&self) -> String {
+        let weekday = WEEKDAY_LABELS
+            .get(self.weekday as usize)
+            .copied()
+            .unwrap_or_default();
+        let day_of_month = self.date.get(8..).unwrap_or(&self.date);
+        format!("{} {}", weekday, day_of_month)
+    }
+}
+
+/// The built-in Italian calendar plus local days from `calendar-days.config.json`
+#[derive(Default)]
+pub struct HolidayCalendar {
+    extra_days: Vec<ExtraDay>,
+}
+
+impl From<CalendarConfig> for HolidayCalendar {
+    fn from(config: CalendarConfig) -> Self {
+        HolidayCalendar {
+            extra_days: config
+                .days
+                .into_iter()
+                .filter(|day| !day.name.trim().is_empty())
+                .collect(),
+        }
+    }
+}
+
+impl HolidayCalendar {
+    /// National holidays win over local ones falling on the same day
+    pub fn holiday(&self, date: NaiveDate) -> Option<String> {
+        italian_holiday(date)
+            .map(str::to_string)
+            .or_else(|| self.extra_day(date, false))
+    }
+
+    pub fn closure(&self, date: NaiveDate) -> Option<String> {
+        self.extra_day(date, true)
+    }
+
+    pub fn day_info(&self, date: NaiveDate) -> DayInfo {
+        let iso_week = date.iso_week();
+        let holiday_name = self.holiday(date);
+        DayInfo {
+            date: date.format("%Y-%m-%d").to_string(),
+            weekday: date.weekday().num_days_from_sunday(),
+            iso_week: format!("{}-W{:02}", iso_week.year(), iso_week.week()),
+            holiday: holiday_name.is_some(),
+            holiday_name,
+            closure: self.closure(date),
+        }
+    }
+
+    /// Every day of `ym` (YYYY-MM), in order
+    pub fn month_days(&self, ym: &str) -> Vec<DayInfo> {
+        let Ok(first) = NaiveDate::parse_from_str(&format!("{}-01", ym), "%Y-%m-%d") else {
+            return Vec::new();
+        };
+        first
+            .iter_days()
+            .take_while(|date| date.month() == first.month())
+            .map(|date| self.day_info(date))
+            .collect()
+    }
+
+    fn extra_day(&self, date: NaiveDate, closure: bool) -> Option<String> {
+        let full = date.format("%Y-%m-%d").to_string();
+        let yearly = date.format("%m-%d").to_string();
+        self.extra_days
+            .iter()
+            .filter(|day| day.closure == closure)
+            .find(|day| {
+                let key = day.date.trim();
+                key == full || key == yearly
+            })
+            .map(|day| day.name.trim().to_string())
+    }
+}
+
+/// Load the calendar config from R2, falling back to national holidays only
+pub async fn load_holiday_calendar(env: &Env) -> HolidayCalendar {
+    HolidayCalendar::from(
+        config::load_json_config::<CalendarConfig>(env, config::CONFIG_CALENDAR).await,
+    )
+}
+
+/// Gregorian Easter Sunday (anonymous Gregorian algorithm)
+fn easter_sunday(year: i32) -> Option<NaiveDate> {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn calendar(json: &str) -> HolidayCalendar {
+        HolidayCalendar::from(serde_json::from_str::<CalendarConfig>(json).unwrap())
+    }
+
+    #[test]
+    fn easter_sunday_for_known_years() {
+        for (year, easter) in [
+            (2000, "2000-04-23"),
+            (2008, "2008-03-23"),
+            (2019, "2019-04-21"),
+            (2024, "2024-03-31"),
+            (2025, "2025-04-20"),
+            (2026, "2026-04-05"),
+            (2038, "2038-04-25"),
+        ] {
+            assert_eq!(easter_sunday(year), Some(date(easter)), "{}", year);
+        }
+    }
+
+    #[test]
+    fn national_holidays() {
+        assert_eq!(italian_holiday(date("2025-04-20")), Some("Pasqua"));
+        assert_eq!(
+            italian_holiday(date("2025-04-21")),
+            Some("Lunedì dell'Angelo")
+        );
+        assert_eq!(italian_holiday(date("2025-12-26")), Some("Santo Stefano"));
+        assert_eq!(italian_holiday(date("2025-10-04")), None);
+        assert_eq!(
+            italian_holiday(date("2026-10-04")),
+            Some("San Francesco d'Assisi")
+        );
+        assert_eq!(italian_holiday(date("2025-04-22")), None);
+    }
+
+    #[test]
+    fn month_days_covers_the_whole_month() {
+        let days = HolidayCalendar::default().month_days("2024-02");
+
+        assert_eq!(days.len(), 29);
+        assert_eq!(days[0].date, "2024-02-01");
+        assert_eq!(days[28].date, "2024-02-29");
+        // 2024-02-01 is a Thursday in ISO week 5
+        assert_eq!(days[0].weekday, 4);
+        assert_eq!(days[0].iso_week, "2024-W05");
+        assert_eq!(days[0].short_label(), "Gio 01");
+        assert!(HolidayCalendar::default().month_days("2024-13").is_empty());
+    }
+
+    #[test]
+    fn month_days_marks_year_boundary_weeks() {
+        let days = HolidayCalendar::default().month_days("2021-01");
+
+        assert_eq!(days[0].iso_week, "2020-W53");
+        assert!(days[0].holiday);
+        assert_eq!(days[0].holiday_name.as_deref(), Some("Capodanno"));
+        assert_eq!(days[3].iso_week, "2021-W01");
+    }
+
+    #[test]
+    fn local_days_and_closures() {
+        let calendar = calendar(
+            r#"{ "days": [
+                { "date": "12-07", "name": "Sant'Ambrogio" },
+                { "date": "12-08", "name": "Patrono" },
+                { "date": "2025-08-14", "name": "Chiusura ambulatori", "closure": true },
+                { "date": "2025-08-13", "name": "  " }
+            ] }"#,
+        );
+        let december = calendar.month_days("2025-12");
+        let august = calendar.month_days("2025-08");
+
+        assert_eq!(december[6].holiday_name.as_deref(), Some("Sant'Ambrogio"));
+        // National holidays win
+        assert_eq!(
+            december[7].holiday_name.as_deref(),
+            Some("Immacolata Concezione")
+        );
+        // Closures are not holidays
+        assert!(!august[13].holiday);
+        assert_eq!(august[13].closure.as_deref(), Some("Chiusura ambulatori"));
+        assert!(!august[12].holiday && august[12].closure.is_none());
+        assert!(calendar.month_days("2026-08")[13].closure.is_none());
+    }
+}
@@ -0,0 +1,278 @@
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use worker::*;
+
+use crate::people::{load_person_shifts, PersonShift};
+use crate::timezone;
+use crate::{error_response, get_config, offset_ym, query_param};
+
+const CALENDAR_TIMEZONE: &str = "Europe/Rome";
+const DEFAULT_PAST_MONTHS: u32 = 1;
+const DEFAULT_FUTURE_MONTHS: u32 = 3;
+const MAX_WINDOW_MONTHS: u32 = 12;
+const ICS_LINE_LIMIT: usize = 75;
+
+// Europe/Rome definition referenced by every TZID in the feed
+const VTIMEZONE_EUROPE_ROME: &[&str] = &[
+    "BEGIN:VTIMEZONE",
+    "TZID:Europe/Rome",
+    "BEGIN:DAYLIGHT",
+    "TZOFFSETFROM:+0100",
+    "TZOFFSETTO:+0200",
+    "TZNAME:CEST",
+    "DTSTART:19700329T020000",
+    "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU",
+    "END:DAYLIGHT",
+    "BEGIN:STANDARD",
+    "TZOFFSETFROM:+0200",
+    "TZOFFSETTO:+0100",
+    "TZNAME:CET",
+    "DTSTART:19701025T030000",
+    "RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU",
+    "END:STANDARD",
+    "END:VTIMEZONE",
+];
+
+struct CalendarEvent {
+    uid: String,
+    summary: String,
+    description: String,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+}
+
+/// API endpoint: iCalendar subscription feed for one person
+pub async fn handle_calendar_feed(
+    req: Request,
+    ctx: RouteContext<Context>,
+    file: String,
+) -> Result<Response> {
+    let person_id = match file.strip_suffix(".ics") {
+        Some(id) if !id.trim().is_empty() => id.trim().to_string(),
+        _ => return error_response("NOT_FOUND", "Expected /api/calendar/<person_id>.ics", 404),
+    };
+
+    let config = match get_config(&ctx.env) {
+        Ok(cfg) => cfg,
+        Err(e) => return error_response("CONFIG_ERROR", &e.to_string(), 500),
+    };
+
+    let url = req.url()?;
+    let past_months = parse_window_param(&url, "past").unwrap_or(DEFAULT_PAST_MONTHS);
+    let future_months = parse_window_param(&url, "ahead").unwrap_or(DEFAULT_FUTURE_MONTHS);
+    let window_months = past_months
+        .checked_add(future_months)
+        .and_then(|months| months.checked_add(1));
+    if window_months.is_none_or(|months| months > MAX_WINDOW_MONTHS) {
+        return error_response(
+            "INVALID_WINDOW",
+            &format!("Calendar window cannot exceed {} months", MAX_WINDOW_MONTHS),
+            400,
+        );
+    }
+
+    let current_ym = Utc::now().format("%Y-%m").to_string();
+    let (start, end) = match (
+        offset_ym(&current_ym, -(past_months as i32))
+            .and_then(|ym| NaiveDate::parse_from_str(&format!("{}-01", ym), "%Y-%m-%d").ok()),
+        offset_ym(&current_ym, future_months as i32 + 1)
+            .and_then(|ym| NaiveDate::parse_from_str(&format!("{}-01", ym), "%Y-%m-%d").ok())
+            .and_then(|next| next.pred_opt()),
+    ) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return error_response("INVALID_WINDOW", "Calendar window is out of range", 400),
+    };
+
+    // Unknown ids still get an (empty) calendar so subscriptions keep working
+    let (person_name, shifts) =
+        match load_person_shifts(&ctx, &config, &person_id, start, end).await {
+            Ok(Some((person, shifts, _))) => (Some(person.label().to_string()), shifts),
+            Ok(None) => (None, Vec::new()),
+            Err(failure) => return failure.into_response(),
+        };
+
+    let mut events: Vec<CalendarEvent> = shifts
+        .iter()
+        .filter_map(|shift| build_event(&person_id, shift))
+        .collect();
+    events.sort_by(|a, b| a.start.cmp(&b.start).then_with(|| a.uid.cmp(&b.uid)));
+
+    let calendar_name = match person_name {
+        Some(name) => format!("Turni - {}", name),
+        None => "Turni".to_string(),
+    };
+    let body = render_calendar(&calendar_name, &events);
+
+    let headers = Headers::new();
+    headers.set("Content-Type", "text/calendar; charset=utf-8")?;
+    headers.set(
+        "Content-Disposition",
+        &format!(
+            "inline; filename=\"{}.ics\"",
+            person_id.replace(['"', '\\'], "_")
+        ),
+    )?;
+    headers.set(
+        "Cache-Control",
+        &format!("public, max-age={}", config.cache_ttl_seconds.max(1)),
+    )?;
+    headers.set("Access-Control-Allow-Origin", "*")?;
+
+    Ok(Response::ok(body)?.with_headers(headers))
+}
+
+fn parse_window_param(url: &Url, name: &str) -> Option<u32> {
+    query_param(url, name).and_then(|v| v.parse::<u32>().ok())
+}
+
+/// Rome wall-clock time, written with `TZID=Europe/Rome`
+fn parse_event_time(value: Option<&str>) -> Option<NaiveDateTime> {
+    timezone::parse_timestamp(value?).map(|time| time.naive_local())
+}
+
+fn build_event(person_id: &str, shift: &PersonShift) -> Option<CalendarEvent> {
+    let start = parse_event_time(shift.start.as_deref())?;
+    let end = parse_event_time(shift.end.as_deref())
+        .filter(|end| *end > start)
+        .unwrap_or(start);
+
+    Some(CalendarEvent {
+        uid: format!(
+            "{}-{}-{}@schedule-viewer",
+            person_id,
+            start.format("%Y%m%dT%H%M%S"),
+            shift.code
+        ),
+        summary: shift.label.clone(),
+        description: shift.code.clone(),
+        start,
+        end,
+    })
+}
+
+fn render_calendar(calendar_name: &str, events: &[CalendarEvent]) -> String {
+    let dtstamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+    let mut lines: Vec<String> = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//schedule-viewer//shifts//IT".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(calendar_name)),
+        format!("X-WR-TIMEZONE:{}", CALENDAR_TIMEZONE),
+    ];
+    lines.extend(VTIMEZONE_EUROPE_ROME.iter().map(|line| line.to_string()));
+
+    for event in events {
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", event.uid));
+        lines.push(format!("DTSTAMP:{}", dtstamp));
+        lines.push(format!(
+            "DTSTART;TZID={}:{}",
+            CALENDAR_TIMEZONE,
+            event.start.format("%Y%m%dT%H%M%S")
+        ));
+        lines.push(format!(
+            "DTEND;TZID={}:{}",
+            CALENDAR_TIMEZONE,
+            event.end.format("%Y%m%dT%H%M%S")
+        ));
+        lines.push(format!("SUMMARY:{}", escape_text(&event.summary)));
+        if !event.description.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape_text(&event.description)));
+        }
+        lines.push("TRANSP:OPAQUE".to_string());
+        lines.push("END:VEVENT".to_string());
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut output = String::new();
+    for line in lines {
+        output.push_str(&fold_line(&line));
+        output.push_str("\r\n");
+    }
+    output
+}
+
+/// Escape TEXT values per RFC 5545 section 3.3.11
+fn escape_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Fold content lines longer than 75 octets without splitting UTF-8 sequences
+fn fold_line(line: &str) -> String {
+    if line.len() <= ICS_LINE_LIMIT {
+        return line.to_string();
+    }
+
+    let mut folded = String::with_capacity(line.len() + line.len() / ICS_LINE_LIMIT * 3);
+    let mut current_len = 0;
+    for ch in line.chars() {
+        let ch_len = ch.len_utf8();
+        // Continuation lines start with a space, which counts towards the limit
+        if current_len + ch_len > ICS_LINE_LIMIT {
+            folded.push_str("\r\n ");
+            current_len = 1;
+        }
+        folded.push(ch);
+        current_len += ch_len;
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_text_follows_rfc_5545() {
+        assert_eq!(
+            escape_text("Turni; Rossi, Mario\\Bianchi\r\nNotte"),
+            r"Turni\; Rossi\, Mario\\Bianchi\nNotte"
+        );
+        assert_eq!(escape_text("Mattino 8:00"), "Mattino 8:00");
+    }
+
+    #[test]
+    fn fold_line_keeps_short_lines() {
+        let line = "S".repeat(ICS_LINE_LIMIT);
+        assert_eq!(fold_line(&line), line);
+    }
+
+    #[test]
+    fn fold_line_splits_at_75_octets() {
+        let line = format!("SUMMARY:{}", "x".repeat(100));
+        let folded = fold_line(&line);
+
+        let parts: Vec<&str> = folded.split("\r\n").collect();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].len(), ICS_LINE_LIMIT);
+        assert!(parts[1].starts_with(' '));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+
+    #[test]
+    fn fold_line_does_not_split_multibyte_characters() {
+        // 74 ASCII octets, then a 2-octet "è" that would straddle the limit
+        let line = format!("{}è{}", "a".repeat(74), "b".repeat(10));
+        let folded = fold_line(&line);
+
+        for part in folded.split("\r\n") {
+            assert!(part.len() <= ICS_LINE_LIMIT);
+        }
+        assert!(folded.starts_with(&format!("{}\r\n è", "a".repeat(74))));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+}
@@ -3,40 +3,80 @@ use chrono::{DateTime, Utc};
 use constant_time_eq::constant_time_eq;
 use hmac::{Hmac, Mac};
 use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 use sha2::Sha256;
-use std::collections::{HashMap, HashSet};
+use source::ScheduleSource;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::RwLock;
 use worker::*;
 
+mod availability;
+mod cache;
+mod changes;
 mod config;
+mod coverage;
+mod export;
+mod groups;
+mod holidays;
+mod ics;
+mod names;
+mod people;
+mod print;
+mod range;
+mod share;
+mod snapshots;
+mod source;
+mod stats;
+mod styles;
+mod timezone;
 mod utils;
+mod violations;
+mod webhooks;
+mod xlsx;
 
-const UPSTREAM_TIMEOUT_MESSAGE: &str = "Upstream request timed out";
 const SESSION_COOKIE: &str = "schedule_viewer_session";
 const VISITOR_COOKIE: &str = "schedule_viewer_vid";
 const LEGACY_ACCESS_COOKIE: &str = "schedule_viewer_access";
 const DEFAULT_SESSION_TTL_SECONDS: u64 = 60 * 60 * 24 * 30;
 const VISITOR_COOKIE_TTL_SECONDS: u64 = 60 * 60 * 24 * 365;
 const TELEMETRY_ARCHIVE_MAX_PART_BYTES: usize = 10 * 1024 * 1024;
+// Must match the polling entry in wrangler.toml `[triggers] crons`
+const SCHEDULE_POLL_CRON: &str = "*/30 * * * *";
+// Session role that unlocks draft/published schedule versions
+const SCHEDULER_ROLE: &str = "scheduler";
 
 // Environment variables
+#[derive(Clone)]
 struct Config {
     api_base_url: String,
     api_token: String,
     api_timeout_ms: u64,
+    api_budget_ms: u64,
+    schedule_source: source::SourceKind,
     cache_ttl_seconds: u64,
+    stale_while_revalidate_seconds: u64,
+    stale_if_error_seconds: u64,
 }
 
-#[derive(Clone)]
-struct CachedSchedule {
-    json: String,
-    fetched_at: DateTime<Utc>,
-}
+// Namespace for month schedules in the tiered cache
+const SCHEDULE_CACHE_NAMESPACE: &str = "schedule";
+
+// Months with a background refresh in flight, so stale hits don't stampede upstream
+static SCHEDULE_REVALIDATING: Lazy<RwLock<HashSet<String>>> =
+    Lazy::new(|| RwLock::new(HashSet::new()));
 
-static SCHEDULE_CACHE: Lazy<RwLock<HashMap<String, CachedSchedule>>> =
-    Lazy::new(|| RwLock::new(HashMap::new()));
+/// Outcome of an in-memory cache lookup, graded by entry age
+enum CacheLookup {
+    /// Younger than the TTL
+    Fresh(MonthShifts),
+    /// Past the TTL but inside the stale-while-revalidate window
+    Revalidate(MonthShifts),
+    /// Past the revalidate window but still usable if upstream fails
+    StaleIfError(MonthShifts),
+    Miss,
+}
 
 // Telemetry structures
 type JsonMap = Map<String, Value>;
@@ -143,20 +183,195 @@ enum TelemetryIngestResult {
 }
 
 // Frontend types (MonthShifts contract)
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Clone)]
 struct MonthShifts {
     ym: String,
     people: Vec<Person>,
-    rows: Vec<Vec<Option<Vec<String>>>>,
+    rows: Vec<Vec<Option<Vec<ShiftCell>>>>,
     codes: Vec<String>,
     #[serde(rename = "shiftNames")]
-    shift_names: HashMap<String, String>,
+    shift_names: BTreeMap<String, String>,
+    /// Colours MetricAid assigns to codes, the fallback for codes missing from `shift-colors.json`
+    #[serde(
+        rename = "upstreamColors",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    upstream_colors: BTreeMap<String, String>,
+    /// Per person (aligned with `people`): shifts from the previous month's last
+    /// evening that run into day 1. Empty when there are none.
+    #[serde(rename = "carryIn", default, skip_serializing_if = "Vec::is_empty")]
+    carry_in: Vec<Option<Vec<ShiftCell>>>,
+    /// Weekday, ISO week and holiday/closure flags for each day of the month
+    #[serde(default)]
+    days: Vec<holidays::DayInfo>,
+    /// Groups from `person-groups.config.json`; `people` follows their order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    groups: Vec<groups::PersonGroup>,
 }
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 struct Person {
     id: String,
+    /// Upstream "fname lname"
     name: String,
+    /// Name from `doctor-names.json` plus the pseudonym, as shown in the grid
+    #[serde(
+        rename = "displayName",
+        default,
+        skip_serializing_if = "String::is_empty"
+    )]
+    display_name: String,
+    /// `displayName` without the pseudonym
+    #[serde(
+        rename = "resolvedName",
+        default,
+        skip_serializing_if = "String::is_empty"
+    )]
+    resolved_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pseudonym: Option<String>,
+    /// Listed in `full-name-overrides.json`: not abbreviated in compact layouts
+    #[serde(rename = "keepFullName", default, skip_serializing_if = "is_false")]
+    keep_full_name: bool,
+    /// ID of the first configured group listing this person
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    group: Option<String>,
+}
+
+impl Person {
+    /// Display name, or the upstream name for months cached before names were resolved
+    fn label(&self) -> &str {
+        if self.display_name.is_empty() {
+            &self.name
+        } else {
+            &self.display_name
+        }
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+/// A single assignment inside a `rows[i][day]` cell.
+/// The default contract only carries the code; `?detail=times` keeps the timing.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+enum ShiftCell {
+    Code(String),
+    Timed(TimedShift),
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+struct TimedShift {
+    code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end: Option<String>,
+    #[serde(rename = "durationMinutes", skip_serializing_if = "Option::is_none")]
+    duration_minutes: Option<i64>,
+    /// Ends after midnight, so it spans its start day and the next
+    #[serde(default, skip_serializing_if = "is_false")]
+    overnight: bool,
+}
+
+/// Where a shift lands in the month matrix
+enum ShiftPlacement {
+    /// 0-based day of the month the shift starts on (Rome time)
+    Day(usize),
+    /// Started on the previous month's last evening and runs into day 1
+    CarryIn,
+}
+
+impl ShiftCell {
+    fn code(&self) -> &str {
+        match self {
+            ShiftCell::Code(code) => code,
+            ShiftCell::Timed(timed) => &timed.code,
+        }
+    }
+
+    fn with_detail(self, detail: ShiftDetail) -> Self {
+        match (self, detail) {
+            (ShiftCell::Timed(timed), ShiftDetail::Codes) => ShiftCell::Code(timed.code),
+            (cell, _) => cell,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ShiftDetail {
+    Codes,
+    Times,
+}
+
+impl ShiftDetail {
+    fn from_query(value: Option<&str>) -> Option<Self> {
+        match value.map(|v| v.trim().to_lowercase()).as_deref() {
+            None | Some("") | Some("codes") => Some(ShiftDetail::Codes),
+            Some("times") => Some(ShiftDetail::Times),
+            _ => None,
+        }
+    }
+}
+
+/// Upstream roster version (`scheduleVersion`). Only `live` is public.
+#[derive(Clone, Copy, PartialEq)]
+enum ScheduleVersion {
+    Live,
+    Draft,
+    Published,
+}
+
+impl ScheduleVersion {
+    fn from_query(value: Option<&str>) -> Option<Self> {
+        match value.map(|v| v.trim().to_lowercase()).as_deref() {
+            None | Some("") | Some("live") => Some(ScheduleVersion::Live),
+            Some("draft") => Some(ScheduleVersion::Draft),
+            Some("published") => Some(ScheduleVersion::Published),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            ScheduleVersion::Live => "live",
+            ScheduleVersion::Draft => "draft",
+            ScheduleVersion::Published => "published",
+        }
+    }
+
+    fn requires_elevated_role(self) -> bool {
+        self != ScheduleVersion::Live
+    }
+
+    /// Cache key for one month; live keeps the bare `ym` used before versions existed
+    fn cache_key(self, ym: &str) -> String {
+        match self {
+            ScheduleVersion::Live => ym.to_string(),
+            other => format!("{}@{}", ym, other.as_str()),
+        }
+    }
+}
+
+impl MonthShifts {
+    /// Project the cached (timed) schedule onto the requested level of detail
+    fn with_detail(mut self, detail: ShiftDetail) -> Self {
+        let cells = self
+            .rows
+            .iter_mut()
+            .flatten()
+            .chain(self.carry_in.iter_mut());
+        for cell in cells.flatten() {
+            *cell = std::mem::take(cell)
+                .into_iter()
+                .map(|entry| entry.with_detail(detail))
+                .collect();
+        }
+        self
+    }
 }
 
 // API Error response
@@ -178,6 +393,8 @@ struct AccessResponse {
     error: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
 }
 
 // Feedback submission types
@@ -203,6 +420,14 @@ struct ViewerSessionClaims {
     vid: String,
     iat: i64,
     exp: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+}
+
+impl ViewerSessionClaims {
+    fn has_role(&self, role: &str) -> bool {
+        self.role.as_deref() == Some(role)
+    }
 }
 
 #[derive(Serialize)]
@@ -217,26 +442,24 @@ struct UpstreamResponse {
     data: Vec<UpstreamShift>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 struct UpstreamShift {
     start_time: String,
-    #[allow(dead_code)]
     end_time: String,
     shift: ShiftDetails,
     user: UserDetails,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 struct ShiftDetails {
     #[allow(dead_code)]
     name: String,
     alias: String,
     #[serde(default)]
-    #[allow(dead_code)]
     color: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 struct UserDetails {
     id: Option<u64>,
     fname: Option<String>,
@@ -266,7 +489,7 @@ fn log_request(req: &Request) {
 }
 
 #[event(fetch)]
-pub async fn main(req: Request, env: Env, _ctx: worker::Context) -> Result<Response> {
+pub async fn main(req: Request, env: Env, ctx: worker::Context) -> Result<Response> {
     log_request(&req);
 
     // Setup panic hook for better error messages
@@ -277,8 +500,8 @@ pub async fn main(req: Request, env: Env, _ctx: worker::Context) -> Result<Respo
         return handle_options_with_origin(&req);
     }
 
-    // Router
-    let router = Router::new();
+    // Router (the worker context rides along so handlers can use wait_until)
+    let router = Router::with_data(ctx);
     router
         .post_async("/api/access", |req, ctx| async move {
             handle_access(req, ctx).await
@@ -289,6 +512,55 @@ pub async fn main(req: Request, env: Env, _ctx: worker::Context) -> Result<Respo
         .get_async("/api/shifts", |req, ctx| async move {
             handle_shifts(req, ctx).await
         })
+        .get_async("/api/shifts/coverage", |req, ctx| async move {
+            coverage::handle_shift_coverage(req, ctx).await
+        })
+        .get_async("/api/shifts/free", |req, ctx| async move {
+            availability::handle_free_people(req, ctx).await
+        })
+        .get_async("/api/shifts/violations", |req, ctx| async move {
+            violations::handle_shift_violations(req, ctx).await
+        })
+        .get_async("/api/shifts/changes", |req, ctx| async move {
+            changes::handle_shift_changes(req, ctx).await
+        })
+        .get_async("/api/export", |req, ctx| async move {
+            export::handle_export(req, ctx).await
+        })
+        .get_async("/api/print", |req, ctx| async move {
+            print::handle_print(req, ctx).await
+        })
+        .post_async("/api/share", |req, ctx| async move {
+            share::handle_create_share(req, ctx).await
+        })
+        .get_async("/api/share/:token", |req, ctx| async move {
+            let token = ctx.param("token").map_or("".to_string(), |v| v.to_string());
+            share::handle_shared(req, ctx, token).await
+        })
+        .get_async("/api/snapshots/:ym", |_req, ctx| async move {
+            let ym = ctx.param("ym").map_or("".to_string(), |v| v.to_string());
+            snapshots::handle_list_snapshots(ctx, ym).await
+        })
+        .get_async("/api/snapshots/:ym/:id", |_req, ctx| async move {
+            let ym = ctx.param("ym").map_or("".to_string(), |v| v.to_string());
+            let id = ctx.param("id").map_or("".to_string(), |v| v.to_string());
+            snapshots::handle_get_snapshot(ctx, ym, id).await
+        })
+        .get_async("/api/stats", |req, ctx| async move {
+            stats::handle_stats(req, ctx).await
+        })
+        .get_async("/api/people/:id/shifts", |req, ctx| async move {
+            let id = ctx
+                .param("id")
+                .map_or("".to_string(), |v| percent_decode(v));
+            people::handle_person_shifts(req, ctx, id).await
+        })
+        .get_async("/api/calendar/:file", |req, ctx| async move {
+            let file = ctx
+                .param("file")
+                .map_or("".to_string(), |v| percent_decode(v));
+            ics::handle_calendar_feed(req, ctx, file).await
+        })
         .get_async("/api/config/:name", |req, ctx| async move {
             let name = ctx.param("name").map_or("".to_string(), |v| v.to_string());
             config::handle_get_config(req, ctx, name).await
@@ -305,6 +577,17 @@ pub async fn main(req: Request, env: Env, _ctx: worker::Context) -> Result<Respo
 
 #[event(scheduled)]
 pub async fn handle_scheduled(event: ScheduledEvent, env: Env, _ctx: ScheduleContext) {
+    if event.cron() == SCHEDULE_POLL_CRON {
+        if !resolve_bool_var(&env, "SCHEDULE_POLL_ENABLED", true) {
+            return;
+        }
+
+        if let Err(error) = webhooks::poll_schedule_changes(&env).await {
+            console_error!("Scheduled schedule poll failed: {:?}", error);
+        }
+        return;
+    }
+
     if !resolve_bool_var(&env, "TELEMETRY_ARCHIVE_TO_R2", true) {
         return;
     }
@@ -329,17 +612,77 @@ pub async fn handle_scheduled(event: ScheduledEvent, env: Env, _ctx: ScheduleCon
     }
 }
 
-async fn handle_shifts(req: Request, ctx: RouteContext<()>) -> Result<Response> {
+async fn handle_shifts(req: Request, ctx: RouteContext<Context>) -> Result<Response> {
     // Parse config from environment
-    let config = match get_config(&ctx) {
+    let config = match get_config(&ctx.env) {
         Ok(cfg) => cfg,
         Err(e) => return error_response("CONFIG_ERROR", &e.to_string(), 500),
     };
 
-    // Validate and extract ym parameter
     let url = req.url()?;
-    let ym = match url.query_pairs().find(|(k, _)| k == "ym") {
-        Some((_, v)) => v.to_string(),
+    let if_none_match = req.headers().get("If-None-Match")?;
+    let detail = match ShiftDetail::from_query(query_param(&url, "detail").as_deref()) {
+        Some(detail) => detail,
+        None => {
+            return error_response(
+                "INVALID_DETAIL",
+                "Invalid detail value. Expected codes or times",
+                400,
+            )
+        }
+    };
+
+    let version = match ScheduleVersion::from_query(query_param(&url, "version").as_deref()) {
+        Some(version) => version,
+        None => {
+            return error_response(
+                "INVALID_VERSION",
+                "Invalid version value. Expected live, draft or published",
+                400,
+            )
+        }
+    };
+
+    if let Some(denied) = check_version_access(&req, &ctx, version) {
+        return denied;
+    }
+
+    let group = query_param(&url, "group")
+        .map(|group| group.trim().to_string())
+        .filter(|group| !group.is_empty());
+    let style_config = if is_truthy_param(query_param(&url, "styled").as_deref()) {
+        Some(styles::load_style_config(&ctx.env).await)
+    } else {
+        None
+    };
+
+    // Multi-month range queries (from/to or from_ym/to_ym)
+    if let Some(range) = range::parse_range_query(&url) {
+        let response = match range {
+            Ok((from, to)) => {
+                range::handle_shifts_range(
+                    &ctx,
+                    &config,
+                    from,
+                    to,
+                    range::RangeOptions {
+                        detail,
+                        version,
+                        group: group.as_deref(),
+                        style_config: style_config.as_ref(),
+                        if_none_match: if_none_match.as_deref(),
+                    },
+                )
+                .await?
+            }
+            Err(message) => return error_response("INVALID_RANGE", &message, 400),
+        };
+        return restrict_to_version_audience(response, version, &req);
+    }
+
+    // Validate and extract ym parameter
+    let ym = match query_param(&url, "ym") {
+        Some(v) => v,
         None => return error_response("MISSING_PARAM", "Missing required parameter: ym", 400),
     };
 
@@ -347,77 +690,307 @@ async fn handle_shifts(req: Request, ctx: RouteContext<()>) -> Result<Response>
         return error_response("INVALID_YM", "Invalid ym format. Expected YYYY-MM", 400);
     }
 
-    // Calculate month boundaries
-    let (start_date, end_date) = match get_month_bounds(&ym) {
-        Ok(bounds) => bounds,
-        Err(e) => return error_response("DATE_ERROR", &e.to_string(), 400),
+    let (mut month_shifts, cache_status) =
+        match load_month_shifts(&ctx, &config, &ym, version).await {
+            Ok(result) => result,
+            Err(failure) => return failure.into_response(),
+        };
+
+    if let Some(group) = &group {
+        if !groups::retain_group(&mut month_shifts, group) {
+            return unknown_group_response(group);
+        }
+    }
+    // Months cached before `days` existed fall back to the built-in calendar
+    if month_shifts.days.is_empty() {
+        month_shifts.days = holidays::HolidayCalendar::default().month_days(&ym);
+    }
+
+    let month_shifts = month_shifts.with_detail(detail);
+    let json = match &style_config {
+        Some(style_config) => {
+            let styles = style_config.resolve(
+                &month_shifts.rows,
+                &month_shifts.days,
+                &month_shifts.codes,
+                &month_shifts.upstream_colors,
+            );
+            serde_json::to_string(&styles::Styled {
+                body: month_shifts,
+                styles,
+            })?
+        }
+        None => serde_json::to_string(&month_shifts)?,
     };
+    let response = build_success_response(
+        json,
+        response_ttl(&config, cache_status),
+        cache_status,
+        if_none_match.as_deref(),
+    )?;
+    restrict_to_version_audience(response, version, &req)
+}
 
-    let cache_ttl_seconds = config.cache_ttl_seconds;
+fn unknown_group_response(group: &str) -> Result<Response> {
+    error_response(
+        "UNKNOWN_GROUP",
+        &format!(
+            "Group {} is not defined in person-groups.config.json",
+            group
+        ),
+        404,
+    )
+}
 
-    if let Some(cached_json) = get_cached_schedule(&ym, cache_ttl_seconds) {
-        return build_success_response(cached_json, cache_ttl_seconds, "HIT");
+/// Error response when the caller may not read `version` (draft/published need a scheduler)
+fn check_version_access(
+    req: &Request,
+    ctx: &RouteContext<Context>,
+    version: ScheduleVersion,
+) -> Option<Result<Response>> {
+    if !version.requires_elevated_role() {
+        return None;
     }
 
-    // Fetch shift display config from R2
-    let bucket = ctx.bucket("CONFIG_BUCKET")?;
-    let config_cache_ttl = ctx
-        .var("CONFIG_CACHE_TTL_SECONDS")
-        .ok()
-        .and_then(|v| v.to_string().parse::<u64>().ok())
-        .unwrap_or(300);
+    match extract_viewer_session(req, ctx) {
+        Some(claims) if claims.has_role(SCHEDULER_ROLE) => None,
+        Some(_) => Some(error_response(
+            "FORBIDDEN",
+            "Draft and published schedules require a scheduler session",
+            403,
+        )),
+        None => Some(error_response(
+            "UNAUTHORIZED",
+            "Sign in as a scheduler to view draft and published schedules",
+            401,
+        )),
+    }
+}
 
-    let shift_display_config =
-        match config::get_shift_display_config(&bucket, config_cache_ttl).await {
-            Ok(cfg) => cfg,
-            Err(e) => {
-                console_log!(
-                    "Failed to fetch shift display config from R2: {:?}, using defaults",
-                    e
-                );
-                config::ShiftDisplayConfig::default()
-            }
-        };
+/// Non-live schedules depend on the caller's session: keep them out of shared
+/// caches and only expose them to the credentialed origin that asked.
+fn restrict_to_version_audience(
+    mut response: Response,
+    version: ScheduleVersion,
+    req: &Request,
+) -> Result<Response> {
+    if !version.requires_elevated_role() {
+        return Ok(response);
+    }
 
-    // Build upstream URL with token as query parameter
-    let upstream_url = format!(
-        "{}/public/schedule?token={}&startDate={}&endDate={}&scheduleVersion=live",
-        config.api_base_url, config.api_token, start_date, end_date
-    );
+    let origin = req
+        .headers()
+        .get("Origin")?
+        .unwrap_or_else(|| "*".to_string());
+    let headers = response.headers_mut();
+    headers.set("Cache-Control", "private, no-cache")?;
+    headers.set("Access-Control-Allow-Origin", &origin)?;
+    headers.set("Access-Control-Allow-Credentials", "true")?;
+    headers.set("Vary", "Origin, Cookie")?;
+    headers.set("X-Schedule-Version", version.as_str())?;
+    Ok(response)
+}
+
+/// Resolve a month's schedule from the in-memory cache or the upstream API.
+/// Returns the timed schedule plus the cache status (HIT/MISS/STALE).
+///
+/// Entries past the TTL are served immediately while a background refresh runs;
+/// older entries are only served when the upstream request fails.
+async fn load_month_shifts(
+    ctx: &RouteContext<Context>,
+    config: &Config,
+    ym: &str,
+    version: ScheduleVersion,
+) -> std::result::Result<(MonthShifts, &'static str), UpstreamFailure> {
+    match get_cached_schedule(&ctx.env, ym, version, config).await {
+        CacheLookup::Fresh(cached) => Ok((cached, "HIT")),
+        CacheLookup::Revalidate(stale) => {
+            schedule_revalidation(ctx, config, ym, version);
+            Ok((stale, "STALE"))
+        }
+        CacheLookup::StaleIfError(stale) => {
+            match refresh_month_shifts(&ctx.env, config, ym, version).await {
+                Ok(month_shifts) => {
+                    schedule_snapshot(ctx, &month_shifts, version);
+                    Ok((month_shifts, "MISS"))
+                }
+                Err(failure) => {
+                    console_error!(
+                        "Serving stale schedule for {} after upstream failure: {}",
+                        ym,
+                        failure.message()
+                    );
+                    Ok((stale, "STALE"))
+                }
+            }
+        }
+        CacheLookup::Miss => {
+            let month_shifts = refresh_month_shifts(&ctx.env, config, ym, version).await?;
+            schedule_snapshot(ctx, &month_shifts, version);
+            Ok((month_shifts, "MISS"))
+        }
+    }
+}
 
-    // Fetch from upstream API with timeout and retry
-    let upstream_data = match fetch_with_retry(&upstream_url, config.api_timeout_ms, 2, None).await
-    {
-        Ok(data) => data,
-        Err(e) => {
-            let message = e.to_string();
-            if message == UPSTREAM_TIMEOUT_MESSAGE {
-                return error_response("UPSTREAM_TIMEOUT", &message, 504);
+/// Refresh a month after the response has been sent, at most once per month at a time
+fn schedule_revalidation(
+    ctx: &RouteContext<Context>,
+    config: &Config,
+    ym: &str,
+    version: ScheduleVersion,
+) {
+    let key = version.cache_key(ym);
+    match SCHEDULE_REVALIDATING.write() {
+        Ok(mut in_flight) => {
+            if !in_flight.insert(key.clone()) {
+                return;
             }
-            return error_response("UPSTREAM_ERROR", &message, 502);
         }
-    };
+        Err(_) => return,
+    }
 
-    // Parse upstream response
-    let upstream: UpstreamResponse = match serde_json::from_str(&upstream_data) {
-        Ok(data) => data,
-        Err(e) => {
-            return error_response(
-                "PARSE_ERROR",
-                &format!("Failed to parse upstream response: {}", e),
-                500,
-            )
+    let env = ctx.env.clone();
+    let config = config.clone();
+    let ym = ym.to_string();
+    ctx.data.wait_until(async move {
+        match refresh_month_shifts(&env, &config, &ym, version).await {
+            Ok(month_shifts) => archive_snapshot(&env, &month_shifts, version).await,
+            Err(failure) => console_error!(
+                "Background refresh failed for {}: {}",
+                key,
+                failure.message()
+            ),
         }
-    };
+        if let Ok(mut in_flight) = SCHEDULE_REVALIDATING.write() {
+            in_flight.remove(&key);
+        }
+    });
+}
+
+/// Archive a refreshed month after the response has been sent
+fn schedule_snapshot(
+    ctx: &RouteContext<Context>,
+    month_shifts: &MonthShifts,
+    version: ScheduleVersion,
+) {
+    if version != ScheduleVersion::Live {
+        return;
+    }
+
+    let env = ctx.env.clone();
+    let month_shifts = month_shifts.clone();
+    ctx.data.wait_until(async move {
+        archive_snapshot(&env, &month_shifts, version).await;
+    });
+}
+
+/// Store a refreshed month in R2. Snapshots only follow the live roster.
+async fn archive_snapshot(env: &Env, month_shifts: &MonthShifts, version: ScheduleVersion) {
+    if version != ScheduleVersion::Live {
+        return;
+    }
+
+    if let Err(e) = snapshots::archive_schedule_snapshot(env, month_shifts).await {
+        console_error!(
+            "Failed to archive schedule snapshot for {}: {:?}",
+            month_shifts.ym,
+            e
+        );
+    }
+}
+
+/// Browser max-age for a schedule response; stale copies should be re-requested soon
+fn response_ttl(config: &Config, cache_status: &str) -> u64 {
+    if cache_status == "STALE" {
+        0
+    } else {
+        config.cache_ttl_seconds
+    }
+}
+
+/// Fetch a month from upstream, bypassing the cache, and store the new version.
+/// Callers archive it with `archive_snapshot`.
+async fn refresh_month_shifts(
+    env: &Env,
+    config: &Config,
+    ym: &str,
+    version: ScheduleVersion,
+) -> std::result::Result<MonthShifts, UpstreamFailure> {
+    // Calculate month boundaries, plus the previous day for overnight shifts into day 1
+    let (start_date, end_date) =
+        get_month_bounds(ym).map_err(|e| UpstreamFailure::Unavailable(e.to_string()))?;
+    let fetch_start = chrono::NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.pred_opt())
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .unwrap_or(start_date);
+
+    // Fetch shift display and name configs from R2
+    let shift_display_config = config::ShiftDisplayConfig::from(
+        config::load_json_config::<config::RawShiftDisplayConfig>(env, config::CONFIG_DISPLAY)
+            .await,
+    );
+    let name_config = names::load_name_config(env).await;
+    let groups_config: config::GroupsConfig =
+        config::load_json_config(env, config::CONFIG_GROUPS).await;
+    let calendar = holidays::load_holiday_calendar(env).await;
+
+    // Fetch from the configured source (MetricAid, or a fixture in offline dev)
+    let source = source::ConfiguredSource::from_env(env, config).await?;
+    let upstream_shifts = source
+        .fetch_shifts(&fetch_start, &end_date, version)
+        .await?;
 
     // Transform to MonthShifts format
-    let month_shifts = transform_to_month_shifts(ym.clone(), upstream.data, &shift_display_config);
+    let month_shifts = transform_to_month_shifts(
+        ym.to_string(),
+        upstream_shifts,
+        &shift_display_config,
+        &name_config,
+        &groups_config,
+        &calendar,
+    );
+    store_schedule_in_cache(env, config, &version.cache_key(ym), &month_shifts).await;
 
-    // Build response with caching
-    let json = serde_json::to_string(&month_shifts)?;
-    store_schedule_in_cache(&ym, json.clone());
+    Ok(month_shifts)
+}
 
-    build_success_response(json, cache_ttl_seconds, "MISS")
+/// Failure modes when talking to the MetricAid API
+enum UpstreamFailure {
+    Timeout(String),
+    Unavailable(String),
+    InvalidPayload(String),
+}
+
+impl UpstreamFailure {
+    fn message(&self) -> &str {
+        match self {
+            UpstreamFailure::Timeout(message)
+            | UpstreamFailure::Unavailable(message)
+            | UpstreamFailure::InvalidPayload(message) => message,
+        }
+    }
+
+    fn into_response(self) -> Result<Response> {
+        match self {
+            UpstreamFailure::Timeout(message) => error_response("UPSTREAM_TIMEOUT", &message, 504),
+            UpstreamFailure::Unavailable(message) => {
+                error_response("UPSTREAM_ERROR", &message, 502)
+            }
+            UpstreamFailure::InvalidPayload(message) => error_response(
+                "PARSE_ERROR",
+                &format!("Failed to parse upstream response: {}", message),
+                500,
+            ),
+        }
+    }
+}
+
+fn resolve_config_cache_ttl(env: &Env) -> u64 {
+    env.var("CONFIG_CACHE_TTL_SECONDS")
+        .ok()
+        .and_then(|v| v.to_string().parse::<u64>().ok())
+        .unwrap_or(300)
 }
 
 async fn flush_events_to_storage(
@@ -634,7 +1207,7 @@ async fn export_daily_archive_from_supabase(
     Ok(())
 }
 
-async fn handle_telemetry(mut req: Request, ctx: RouteContext<()>) -> Result<Response> {
+async fn handle_telemetry(mut req: Request, ctx: RouteContext<Context>) -> Result<Response> {
     let origin = req
         .headers()
         .get("Origin")?
@@ -862,7 +1435,7 @@ async fn handle_telemetry(mut req: Request, ctx: RouteContext<()>) -> Result<Res
         .with_status(status_code))
 }
 
-async fn handle_feedback(mut req: Request, ctx: RouteContext<()>) -> Result<Response> {
+async fn handle_feedback(mut req: Request, ctx: RouteContext<Context>) -> Result<Response> {
     let origin = req
         .headers()
         .get("Origin")?
@@ -996,7 +1569,7 @@ async fn handle_feedback(mut req: Request, ctx: RouteContext<()>) -> Result<Resp
 }
 
 async fn insert_feedback_to_supabase(
-    ctx: &RouteContext<()>,
+    ctx: &RouteContext<Context>,
     feedback_text: &str,
     signature: Option<&str>,
     metadata: &JsonMap,
@@ -1123,70 +1696,165 @@ async fn archive_feedback_to_r2(
     Ok(())
 }
 
-fn get_cached_schedule(ym: &str, ttl_seconds: u64) -> Option<String> {
-    if ttl_seconds == 0 {
-        return None;
+async fn get_cached_schedule(
+    env: &Env,
+    ym: &str,
+    version: ScheduleVersion,
+    config: &Config,
+) -> CacheLookup {
+    if config.cache_ttl_seconds == 0 {
+        return CacheLookup::Miss;
     }
 
-    let cached = {
-        let cache = SCHEDULE_CACHE.read().ok()?;
-        cache.get(ym).cloned()
-    }?;
-
-    let age_seconds = Utc::now()
-        .signed_duration_since(cached.fetched_at)
-        .num_seconds();
+    let cache = cache::TieredCache::from_env(env, SCHEDULE_CACHE_NAMESPACE);
+    let entry = match cache
+        .get(&version.cache_key(ym), config.cache_ttl_seconds)
+        .await
+    {
+        Some(entry) => entry,
+        None => return CacheLookup::Miss,
+    };
 
-    if age_seconds < ttl_seconds as i64 {
-        Some(cached.json)
-    } else {
-        if let Ok(mut cache) = SCHEDULE_CACHE.write() {
-            cache.remove(ym);
+    let schedule = match serde_json::from_str::<MonthShifts>(&entry.value) {
+        Ok(schedule) => schedule,
+        Err(e) => {
+            console_error!("Discarding unreadable cached schedule for {}: {}", ym, e);
+            return CacheLookup::Miss;
         }
-        None
+    };
+
+    match entry.freshness(
+        config.cache_ttl_seconds,
+        config.stale_while_revalidate_seconds,
+        config.stale_if_error_seconds,
+    ) {
+        cache::Freshness::Fresh => CacheLookup::Fresh(schedule),
+        cache::Freshness::Revalidate => CacheLookup::Revalidate(schedule),
+        cache::Freshness::StaleIfError => CacheLookup::StaleIfError(schedule),
+        cache::Freshness::Expired => CacheLookup::Miss,
     }
 }
 
-fn store_schedule_in_cache(ym: &str, json: String) {
-    if let Ok(mut cache) = SCHEDULE_CACHE.write() {
-        cache.insert(
-            ym.to_string(),
-            CachedSchedule {
-                json,
-                fetched_at: Utc::now(),
-            },
-        );
-    }
+async fn store_schedule_in_cache(env: &Env, config: &Config, key: &str, schedule: &MonthShifts) {
+    let json = match serde_json::to_string(schedule) {
+        Ok(json) => json,
+        Err(e) => {
+            console_error!("Failed to serialize schedule {} for caching: {}", key, e);
+            return;
+        }
+    };
+
+    // Keep entries around for the stale windows, not just the TTL
+    let retain_seconds = config.cache_ttl_seconds
+        + config
+            .stale_while_revalidate_seconds
+            .max(config.stale_if_error_seconds);
+    cache::TieredCache::from_env(env, SCHEDULE_CACHE_NAMESPACE)
+        .put(key, json, retain_seconds)
+        .await;
 }
 
-fn build_success_response(json: String, ttl_seconds: u64, cache_status: &str) -> Result<Response> {
+/// JSON response with caching headers and a strong ETag.
+/// Answers 304 Not Modified when `If-None-Match` already names the current body.
+fn build_success_response(
+    json: String,
+    ttl_seconds: u64,
+    cache_status: &str,
+    if_none_match: Option<&str>,
+) -> Result<Response> {
+    let etag = utils::strong_etag(json.as_bytes());
+
     let headers = Headers::new();
     headers.set("Content-Type", "application/json")?;
     headers.set(
         "Cache-Control",
         &format!("public, max-age={}", ttl_seconds.max(1)),
     )?;
+    headers.set("ETag", &etag)?;
     headers.set("Access-Control-Allow-Origin", "*")?;
+    headers.set("Access-Control-Expose-Headers", "ETag, X-Cache-Status")?;
     headers.set("Vary", "Origin")?;
     headers.set("X-Cache-Status", cache_status)?;
 
+    if if_none_match.is_some_and(|header| utils::etag_matches(header, &etag)) {
+        return Ok(Response::empty()?.with_status(304).with_headers(headers));
+    }
+
     Ok(Response::ok(json)?.with_headers(headers))
 }
 
-fn get_config(ctx: &RouteContext<()>) -> Result<Config> {
+/// Non-JSON counterpart of `build_success_response` for files (exports, print pages)
+fn build_file_response(
+    body: Vec<u8>,
+    content_type: &str,
+    content_disposition: &str,
+    ttl_seconds: u64,
+    cache_status: &str,
+    if_none_match: Option<&str>,
+) -> Result<Response> {
+    let etag = utils::strong_etag(&body);
+
+    let headers = Headers::new();
+    headers.set("Content-Type", content_type)?;
+    headers.set("Content-Disposition", content_disposition)?;
+    headers.set(
+        "Cache-Control",
+        &format!("public, max-age={}", ttl_seconds.max(1)),
+    )?;
+    headers.set("ETag", &etag)?;
+    headers.set("Access-Control-Allow-Origin", "*")?;
+    headers.set(
+        "Access-Control-Expose-Headers",
+        "ETag, X-Cache-Status, Content-Disposition",
+    )?;
+    headers.set("Vary", "Origin")?;
+    headers.set("X-Cache-Status", cache_status)?;
+
+    if if_none_match.is_some_and(|header| utils::etag_matches(header, &etag)) {
+        return Ok(Response::empty()?.with_status(304).with_headers(headers));
+    }
+
+    Ok(Response::from_bytes(body)?.with_headers(headers))
+}
+
+fn get_config(env: &Env) -> Result<Config> {
+    let schedule_source = source::SourceKind::from_env(env);
+    // Fixture mode runs without MetricAid credentials
+    let api_token = match env.secret("API_TOKEN") {
+        Ok(token) => token.to_string(),
+        Err(_) if schedule_source == source::SourceKind::Fixture => String::new(),
+        Err(e) => return Err(e),
+    };
+
     Ok(Config {
-        api_base_url: ctx.var("API_BASE_URL")?.to_string(),
-        api_token: ctx.secret("API_TOKEN")?.to_string(),
-        api_timeout_ms: ctx
+        api_base_url: env.var("API_BASE_URL")?.to_string(),
+        api_token,
+        api_timeout_ms: env
             .var("API_TIMEOUT_MS")?
             .to_string()
             .parse()
             .unwrap_or(8000),
-        cache_ttl_seconds: ctx
+        api_budget_ms: env
+            .var("API_BUDGET_MS")
+            .ok()
+            .and_then(|v| v.to_string().parse().ok())
+            .unwrap_or(20000),
+        schedule_source,
+        cache_ttl_seconds: env
             .var("CACHE_TTL_SECONDS")?
             .to_string()
             .parse()
             .unwrap_or(900),
+        stale_while_revalidate_seconds: env
+            .var("CACHE_STALE_WHILE_REVALIDATE_SECONDS")
+            .ok()
+            .and_then(|v| v.to_string().parse().ok())
+            .unwrap_or(300),
+        stale_if_error_seconds: env
+            .var("CACHE_STALE_IF_ERROR_SECONDS")
+            .ok()
+            .and_then(|v| v.to_string().parse().ok())
+            .unwrap_or(86400),
     })
 }
 
@@ -1204,6 +1872,26 @@ fn is_valid_ym(ym: &str) -> bool {
     }
 }
 
+/// Shift a YYYY-MM string by a number of months (negative goes backwards)
+fn offset_ym(ym: &str, months: i32) -> Option<String> {
+    let (year, month) = ym.split_once('-')?;
+    let year: i32 = year.parse().ok()?;
+    let month: i32 = month.parse().ok()?;
+
+    let index = year * 12 + (month - 1) + months;
+    let shifted = format!(
+        "{:04}-{:02}",
+        index.div_euclid(12),
+        index.rem_euclid(12) + 1
+    );
+
+    if is_valid_ym(&shifted) {
+        Some(shifted)
+    } else {
+        None
+    }
+}
+
 fn get_month_bounds(ym: &str) -> Result<(String, String)> {
     let parts: Vec<&str> = ym.split('-').collect();
     let year: i32 = parts[0]
@@ -1232,71 +1920,46 @@ fn get_month_bounds(ym: &str) -> Result<(String, String)> {
     Ok((start_date, end_date))
 }
 
-async fn fetch_with_retry(
-    url: &str,
-    _timeout_ms: u64,
-    max_retries: u32,
-    headers: Option<&[(String, String)]>,
-) -> Result<String> {
-    let mut retries = 0;
-
-    loop {
-        let mut request_init = RequestInit::new();
-        request_init.with_method(Method::Get);
-
-        let mut request = Request::new_with_init(url, &request_init)?;
-
-        if let Some(headers) = headers {
-            for (name, value) in headers {
-                request.headers_mut()?.set(name, value)?;
-            }
-        }
-
-        match Fetch::Request(request).send().await {
-            Ok(mut response) => {
-                if response.status_code() >= 200 && response.status_code() < 300 {
-                    return response.text().await;
-                } else if response.status_code() >= 500 && retries < max_retries {
-                    retries += 1;
-                    continue;
-                } else {
-                    return Err(Error::RustError(format!(
-                        "Upstream returned status {}",
-                        response.status_code()
-                    )));
-                }
-            }
-            Err(_e) if retries < max_retries => {
-                retries += 1;
-                continue;
-            }
-            Err(e) => return Err(e),
-        }
-    }
-}
-
 fn transform_to_month_shifts(
     ym: String,
     shifts: Vec<UpstreamShift>,
     shift_display_config: &config::ShiftDisplayConfig,
+    name_config: &names::NameConfig,
+    groups_config: &config::GroupsConfig,
+    calendar: &holidays::HolidayCalendar,
 ) -> MonthShifts {
+    // Place each shift on the day it starts in Rome time. Only the previous
+    // month's overnight shifts are kept from the extra day fetched before the month.
+    let days_in_month = get_days_in_month(&ym);
+    let month_start = chrono::NaiveDate::parse_from_str(&format!("{}-01", ym), "%Y-%m-%d").ok();
+    let placed: Vec<(ShiftPlacement, UpstreamShift)> = shifts
+        .into_iter()
+        .filter_map(|shift| {
+            let placement = place_shift(&shift, month_start?, days_in_month)?;
+            Some((placement, shift))
+        })
+        .collect();
+
     // Extract unique people
     let mut people_map: HashMap<String, Person> = HashMap::new();
     let mut shift_codes: HashSet<String> = HashSet::new();
-    let mut shift_names: HashMap<String, String> = HashMap::new();
-
-    for shift in &shifts {
-        let fname = shift.user.fname.as_deref().unwrap_or("Unknown");
-        let lname = shift.user.lname.as_deref().unwrap_or("");
-
-        let user_id = shift
-            .user
-            .id
-            .map(|id| id.to_string())
-            .unwrap_or_else(|| format!("{}_{}", fname, lname));
-        people_map.entry(user_id.clone()).or_insert_with(|| Person {
-            id: user_id.clone(),
-            name: format!("{} {}", fname, lname).trim().to_string(),
+    let mut shift_names: BTreeMap<String, String> = BTreeMap::new();
+    let mut upstream_colors: BTreeMap<String, String> = BTreeMap::new();
+
+    for (_, shift) in &placed {
+        let user_id = resolve_user_id(&shift.user);
+        people_map.entry(user_id.clone()).or_insert_with(|| {
+            let name = resolve_user_name(&shift.user);
+            let display = name_config.display_name(&user_id, &name);
+            Person {
+                id: user_id.clone(),
+                keep_full_name: name_config.keeps_full_name(&display.name),
+                name,
+                display_name: display.display,
+                resolved_name: display.name,
+                pseudonym: display.pseudonym,
+                group: None,
+            }
         });
 
         // Extract shift code from alias (remove time portion)
@@ -1305,18 +1968,23 @@ fn transform_to_month_shifts(
             shift_codes.insert(code.clone());
             let resolved_label = shift_display_config.resolve_label(&code, &shift.shift.alias);
             shift_names.insert(code.clone(), resolved_label);
+            if let Some(color) = shift.shift.color.as_deref().map(str::trim) {
+                if !color.is_empty() {
+                    upstream_colors
+                        .entry(code.clone())
+                        .or_insert_with(|| color.to_string());
+                }
+            }
         }
     }
 
-    // Sort people by ID for consistency
+    // Order people by configured group, then by name
     let mut people: Vec<Person> = people_map.values().cloned().collect();
-    people.sort_by(|a, b| a.id.cmp(&b.id));
-
-    // Get days in month
-    let days_in_month = get_days_in_month(&ym);
+    let groups = groups::assign_groups(&mut people, groups_config);
 
     // Build rows matrix (people x days) - each cell can have multiple shifts
-    let mut rows: Vec<Vec<Option<Vec<String>>>> = vec![vec![None; days_in_month]; people.len()];
+    let mut rows: Vec<Vec<Option<Vec<ShiftCell>>>> = vec![vec![None; days_in_month]; people.len()];
+    let mut carry_in: Vec<Option<Vec<ShiftCell>>> = vec![None; people.len()];
 
     // Create person_id -> index mapping
     let person_indices: HashMap<String, usize> = people
@@ -1326,44 +1994,105 @@ fn transform_to_month_shifts(
         .collect();
 
     // Fill in the matrix - support multiple shifts per day
-    for shift in shifts {
-        let fname = shift.user.fname.as_deref().unwrap_or("Unknown");
-        let lname = shift.user.lname.as_deref().unwrap_or("");
-
-        let user_id = shift
-            .user
-            .id
-            .map(|id| id.to_string())
-            .unwrap_or_else(|| format!("{}_{}", fname, lname));
+    for (placement, shift) in placed {
+        let user_id = resolve_user_id(&shift.user);
         if let Some(&person_idx) = person_indices.get(&user_id) {
-            // Extract day from start_time (format: "YYYY-MM-DD HH:MM:SS")
-            if let Some(day) = extract_day_from_datetime(&shift.start_time) {
-                if day > 0 && day <= days_in_month {
-                    let code = extract_shift_code(&shift.shift.alias, shift_display_config);
-                    // Append to existing shifts for this day
-                    if let Some(ref mut shift_codes) = rows[person_idx][day - 1] {
-                        shift_codes.push(code);
-                    } else {
-                        rows[person_idx][day - 1] = Some(vec![code]);
-                    }
-                }
-            }
+            let code = extract_shift_code(&shift.shift.alias, shift_display_config);
+            let entry = ShiftCell::Timed(build_timed_shift(code, &shift));
+            let cell = match placement {
+                ShiftPlacement::Day(day) => &mut rows[person_idx][day],
+                ShiftPlacement::CarryIn => &mut carry_in[person_idx],
+            };
+            // Append to existing shifts for this day
+            cell.get_or_insert_with(Vec::new).push(entry);
         }
     }
+    if carry_in.iter().all(Option::is_none) {
+        carry_in.clear();
+    }
 
     // Convert codes to sorted vec
     let mut codes: Vec<String> = shift_codes.into_iter().collect();
     codes.sort();
 
+    let days = calendar.month_days(&ym);
+
     MonthShifts {
         ym,
         people,
         rows,
         codes,
         shift_names,
+        upstream_colors,
+        carry_in,
+        days,
+        groups,
     }
 }
 
+fn place_shift(
+    shift: &UpstreamShift,
+    month_start: chrono::NaiveDate,
+    days_in_month: usize,
+) -> Option<ShiftPlacement> {
+    let start = timezone::parse_timestamp(&shift.start_time)?;
+    let start_day = start.date_naive();
+    if start_day < month_start {
+        let end = timezone::parse_timestamp(&shift.end_time)?;
+        let runs_into_month = start_day.succ_opt() == Some(month_start)
+            && end.naive_local() > month_start.and_hms_opt(0, 0, 0)?;
+        return runs_into_month.then_some(ShiftPlacement::CarryIn);
+    }
+
+    let day = start_day.signed_duration_since(month_start).num_days() as usize;
+    (day < days_in_month).then_some(ShiftPlacement::Day(day))
+}
+
+fn build_timed_shift(code: String, shift: &UpstreamShift) -> TimedShift {
+    let start = timezone::parse_timestamp(&shift.start_time);
+    let end = timezone::parse_timestamp(&shift.end_time);
+    // Offsets are kept, so durations across a DST change are the real elapsed time
+    let duration_minutes = match (start, end) {
+        (Some(start), Some(end)) if end >= start => {
+            Some(end.signed_duration_since(start).num_minutes())
+        }
+        _ => None,
+    };
+    let overnight = match (start, end) {
+        (Some(start), Some(end)) => start
+            .date_naive()
+            .succ_opt()
+            .and_then(|next_day| next_day.and_hms_opt(0, 0, 0))
+            .is_some_and(|midnight| end.naive_local() > midnight),
+        _ => false,
+    };
+
+    TimedShift {
+        code,
+        start: start.as_ref().map(timezone::format_timestamp),
+        end: end.as_ref().map(timezone::format_timestamp),
+        duration_minutes,
+        overnight,
+    }
+}
+
+/// Stable person id: the upstream id, or `fname_lname` when the id is missing
+fn resolve_user_id(user: &UserDetails) -> String {
+    let fname = user.fname.as_deref().unwrap_or("Unknown");
+    let lname = user.lname.as_deref().unwrap_or("");
+
+    user.id
+        .map(|id| id.to_string())
+        .unwrap_or_else(|| format!("{}_{}", fname, lname))
+}
+
+fn resolve_user_name(user: &UserDetails) -> String {
+    let fname = user.fname.as_deref().unwrap_or("Unknown");
+    let lname = user.lname.as_deref().unwrap_or("");
+
+    format!("{} {}", fname, lname).trim().to_string()
+}
+
 fn get_days_in_month(ym: &str) -> usize {
     let parts: Vec<&str> = ym.split('-').collect();
     let year: i32 = parts[0].parse().unwrap();
@@ -1384,16 +2113,6 @@ fn get_days_in_month(ym: &str) -> usize {
     days as usize
 }
 
-fn extract_day_from_datetime(datetime: &str) -> Option<usize> {
-    // Parse "YYYY-MM-DD HH:MM:SS" format
-    let parts: Vec<&str> = datetime.split_whitespace().next()?.split('-').collect();
-    if parts.len() == 3 {
-        parts[2].parse().ok()
-    } else {
-        None
-    }
-}
-
 fn extract_shift_code(alias: &str, shift_display_config: &config::ShiftDisplayConfig) -> String {
     // Extract shift code from alias like "RATM 8:00AM - 2:00PM" -> "RATM"
     // or "FT 8:30am - 6:30" -> "FT"
@@ -1408,6 +2127,40 @@ fn extract_shift_code(alias: &str, shift_display_config: &config::ShiftDisplayCo
     normalised
 }
 
+/// `1`/`true`/`yes` switch an optional feature on
+fn is_truthy_param(value: Option<&str>) -> bool {
+    matches!(
+        value.map(|v| v.trim().to_ascii_lowercase()).as_deref(),
+        Some("1" | "true" | "yes")
+    )
+}
+
+fn query_param(url: &Url, name: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.to_string())
+}
+
+/// Decode %XX escapes in a path segment (person ids may fall back to `fname_lname`)
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(byte) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
 fn extract_non_empty_header(req: &Request, name: &str) -> Option<String> {
     req.headers()
         .get(name)
@@ -1485,7 +2238,7 @@ fn append_cookie(headers: &Headers, cookie: &str) -> Result<()> {
     headers.append("Set-Cookie", cookie)
 }
 
-fn resolve_session_signing_secret(ctx: &RouteContext<()>) -> Option<String> {
+fn resolve_session_signing_secret(ctx: &RouteContext<Context>) -> Option<String> {
     ctx.secret("SESSION_SECRET")
         .ok()
         .map(|secret| secret.to_string())
@@ -1496,7 +2249,7 @@ fn resolve_session_signing_secret(ctx: &RouteContext<()>) -> Option<String> {
         })
 }
 
-fn resolve_session_ttl_seconds(ctx: &RouteContext<()>) -> u64 {
+fn resolve_session_ttl_seconds(ctx: &RouteContext<Context>) -> u64 {
     ctx.var("SESSION_TTL_SECONDS")
         .ok()
         .and_then(|value| value.to_string().parse::<u64>().ok())
@@ -1504,10 +2257,11 @@ fn resolve_session_ttl_seconds(ctx: &RouteContext<()>) -> u64 {
         .unwrap_or(DEFAULT_SESSION_TTL_SECONDS)
 }
 
-fn sign_viewer_session(claims: &ViewerSessionClaims, secret: &str) -> Result<String> {
+/// `<base64url JSON payload>.<base64url HMAC-SHA256>`, shared by sessions and share links
+fn sign_claims<T: Serialize>(claims: &T, secret: &str) -> Result<String> {
     let payload = serde_json::to_vec(claims)
         .map(|json| URL_SAFE_NO_PAD.encode(json))
-        .map_err(|e| Error::RustError(format!("Failed to encode session payload: {}", e)))?;
+        .map_err(|e| Error::RustError(format!("Failed to encode token payload: {}", e)))?;
 
     let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
         .map_err(|_| Error::RustError("Invalid session secret".to_string()))?;
@@ -1517,7 +2271,8 @@ fn sign_viewer_session(claims: &ViewerSessionClaims, secret: &str) -> Result<Str
     Ok(format!("{}.{}", payload, signature))
 }
 
-fn verify_viewer_session(token: &str, secret: &str) -> Option<ViewerSessionClaims> {
+/// Check the signature and decode the payload; expiry is up to the claim type
+fn verify_claims<T: DeserializeOwned>(token: &str, secret: &str) -> Option<T> {
     let (payload, signature) = token.split_once('.')?;
     let expected_signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
 
@@ -1526,7 +2281,15 @@ fn verify_viewer_session(token: &str, secret: &str) -> Option<ViewerSessionClaim
     mac.verify_slice(&expected_signature).ok()?;
 
     let decoded_payload = URL_SAFE_NO_PAD.decode(payload).ok()?;
-    let claims: ViewerSessionClaims = serde_json::from_slice(&decoded_payload).ok()?;
+    serde_json::from_slice(&decoded_payload).ok()
+}
+
+fn sign_viewer_session(claims: &ViewerSessionClaims, secret: &str) -> Result<String> {
+    sign_claims(claims, secret)
+}
+
+fn verify_viewer_session(token: &str, secret: &str) -> Option<ViewerSessionClaims> {
+    let claims: ViewerSessionClaims = verify_claims(token, secret)?;
     let now = Utc::now().timestamp();
 
     if claims.exp <= now || claims.sid.trim().is_empty() || claims.vid.trim().is_empty() {
@@ -1536,28 +2299,33 @@ fn verify_viewer_session(token: &str, secret: &str) -> Option<ViewerSessionClaim
     Some(claims)
 }
 
-fn extract_viewer_session(req: &Request, ctx: &RouteContext<()>) -> Option<ViewerSessionClaims> {
+fn extract_viewer_session(
+    req: &Request,
+    ctx: &RouteContext<Context>,
+) -> Option<ViewerSessionClaims> {
     let token = read_cookie(req, SESSION_COOKIE)?;
     let secret = resolve_session_signing_secret(ctx)?;
     verify_viewer_session(&token, &secret)
 }
 
-fn has_access_token(req: &Request, ctx: &RouteContext<()>) -> bool {
+fn has_access_token(req: &Request, ctx: &RouteContext<Context>) -> bool {
     extract_viewer_session(req, ctx).is_some()
 }
 
-async fn handle_check_access(req: Request, ctx: RouteContext<()>) -> Result<Response> {
+async fn handle_check_access(req: Request, ctx: RouteContext<Context>) -> Result<Response> {
     let origin = req
         .headers()
         .get("Origin")?
         .unwrap_or_else(|| "*".to_string());
 
-    let has_access = has_access_token(&req, &ctx);
+    let session = extract_viewer_session(&req, &ctx);
+    let has_access = session.is_some();
 
     let response = AccessResponse {
         success: has_access,
         error: None,
         token: None,
+        role: session.and_then(|claims| claims.role),
     };
 
     let json = serde_json::to_string(&response)?;
@@ -1573,7 +2341,7 @@ async fn handle_check_access(req: Request, ctx: RouteContext<()>) -> Result<Resp
     Ok(Response::ok(json)?.with_headers(headers))
 }
 
-async fn handle_access(mut req: Request, ctx: RouteContext<()>) -> Result<Response> {
+async fn handle_access(mut req: Request, ctx: RouteContext<Context>) -> Result<Response> {
     // Get origin for CORS
     let origin = req
         .headers()
@@ -1605,13 +2373,24 @@ async fn handle_access(mut req: Request, ctx: RouteContext<()>) -> Result<Respon
     let expected = expected_password.trim();
 
     // Constant-time comparison to prevent timing attacks
-    let is_valid = constant_time_eq(candidate.as_bytes(), expected.as_bytes());
+    let is_viewer = constant_time_eq(candidate.as_bytes(), expected.as_bytes());
+    // A separate scheduler password grants the elevated role (draft/published versions)
+    let is_scheduler = !is_viewer
+        && ctx
+            .secret("SCHEDULER_ACCESS_PASSWORD")
+            .map(|secret| secret.to_string())
+            .ok()
+            .filter(|secret| !secret.trim().is_empty())
+            .is_some_and(|secret| constant_time_eq(candidate.as_bytes(), secret.trim().as_bytes()));
+    let is_valid = is_viewer || is_scheduler;
+    let role = is_scheduler.then(|| SCHEDULER_ROLE.to_string());
 
     if !is_valid {
         let response = AccessResponse {
             success: false,
             error: Some("Password non valida. Riprova.".to_string()),
             token: None,
+            role: None,
         };
 
         let json = serde_json::to_string(&response)?;
@@ -1635,6 +2414,7 @@ async fn handle_access(mut req: Request, ctx: RouteContext<()>) -> Result<Respon
             vid: visitor_id.clone(),
             iat: now,
             exp: session_exp,
+            role: role.clone(),
         },
         &session_secret,
     )?;
@@ -1643,6 +2423,7 @@ async fn handle_access(mut req: Request, ctx: RouteContext<()>) -> Result<Respon
         success: true,
         error: None,
         token: None,
+        role,
     };
 
     let json = serde_json::to_string(&response)?;
@@ -1685,7 +2466,7 @@ fn build_cors_headers(origin: &str) -> Result<Headers> {
     headers.set("Access-Control-Allow-Methods", "GET, POST, OPTIONS")?;
     headers.set(
         "Access-Control-Allow-Headers",
-        "Content-Type, Authorization",
+        "Content-Type, Authorization, If-None-Match",
     )?;
     headers.set("Access-Control-Allow-Credentials", "true")?;
     headers.set("Access-Control-Max-Age", "86400")?;
@@ -0,0 +1,250 @@
+use futures::future::join;
+use indexmap::IndexMap;
+use worker::*;
+
+use crate::config::{self, DoctorNamesConfig, FullNameOverrides};
+
+/// Resolved name parts for one person (mirrors `DoctorDisplayName` in the frontend)
+pub struct DisplayName {
+    /// Base name without the pseudonym
+    pub name: String,
+    pub pseudonym: Option<String>,
+    /// `name` followed by the pseudonym, as shown in the grid
+    pub display: String,
+}
+
+/// `doctor-names.json` and `full-name-overrides.json`, applied when a month is transformed
+#[derive(Default)]
+pub struct NameConfig {
+    pub doctor_names: DoctorNamesConfig,
+    pub full_name_overrides: FullNameOverrides,
+}
+
+impl NameConfig {
+    /// Same resolution as `getDoctorDisplayName` in `src/lib/doctor-names.ts`
+    pub fn display_name(&self, id: &str, api_name: &str) -> DisplayName {
+        let raw_base = self.resolve_base_name(id, api_name);
+        let base = raw_base.trim();
+        let display = append_pseudonym(&raw_base, api_name);
+
+        let Some(pseudonym) = extract_pseudonym(api_name) else {
+            return DisplayName {
+                name: if base.is_empty() {
+                    display.clone()
+                } else {
+                    base.to_string()
+                },
+                pseudonym: None,
+                display,
+            };
+        };
+
+        if base.is_empty() || base == pseudonym {
+            return DisplayName {
+                name: display.clone(),
+                pseudonym: None,
+                display,
+            };
+        }
+
+        let stripped = strip_pseudonym(base, &pseudonym);
+        if stripped.is_empty() {
+            return DisplayName {
+                name: pseudonym,
+                pseudonym: None,
+                display,
+            };
+        }
+
+        let show_pseudonym = stripped != pseudonym;
+        DisplayName {
+            name: stripped,
+            pseudonym: show_pseudonym.then_some(pseudonym),
+            display,
+        }
+    }
+
+    pub fn keeps_full_name(&self, name: &str) -> bool {
+        self.full_name_overrides.contains(name)
+    }
+
+    /// Lookup order: ID, exact upstream name, case-insensitive key, then a
+    /// surname match against the configured names
+    fn resolve_base_name(&self, id: &str, api_name: &str) -> String {
+        let names = &self.doctor_names.names;
+        let lookup = |key: &str| names.get(key).filter(|name| !name.is_empty()).cloned();
+
+        if let Some(name) = lookup(id).or_else(|| lookup(api_name)) {
+            return name;
+        }
+
+        let trimmed = api_name.trim();
+        if trimmed.is_empty() {
+            return id.to_string();
+        }
+        if let Some(name) = lookup(trimmed) {
+            return name;
+        }
+
+        let search = trimmed.to_lowercase();
+        if let Some((_, name)) = js_entries(names).find(|(key, _)| key.to_lowercase() == search) {
+            return name.clone();
+        }
+
+        let search_last_name = search.split_whitespace().last().unwrap_or_default();
+        for (_, full_name) in js_entries(names) {
+            let full_name_lower = full_name.to_lowercase();
+            let last_name = full_name_lower
+                .split_whitespace()
+                .last()
+                .unwrap_or_default();
+            if last_name == search_last_name || full_name_lower.contains(search_last_name) {
+                return full_name.clone();
+            }
+        }
+
+        trimmed.to_string()
+    }
+}
+
+/// Entries in JavaScript `Object.entries` order: integer keys (API IDs) ascending,
+/// then every other key in file order
+fn js_entries(names: &IndexMap<String, String>) -> impl Iterator<Item = (&String, &String)> {
+    let mut index_keys: Vec<(u32, (&String, &String))> = names
+        .iter()
+        .filter_map(|entry| Some((js_array_index(entry.0)?, entry)))
+        .collect();
+    index_keys.sort_by_key(|(index, _)| *index);
+
+    index_keys.into_iter().map(|(_, entry)| entry).chain(
+        names
+            .iter()
+            .filter(|(key, _)| js_array_index(key).is_none()),
+    )
+}
+
+/// Keys JavaScript treats as array indices: canonical decimals below 2^32 - 1
+fn js_array_index(key: &str) -> Option<u32> {
+    if key.is_empty() || (key.len() > 1 && key.starts_with('0')) {
+        return None;
+    }
+    if !key.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    key.parse::<u32>().ok().filter(|index| *index < u32::MAX)
+}
+
+/// Load both name configs from R2, falling back to defaults on failure
+pub async fn load_name_config(env: &Env) -> NameConfig {
+    let (doctor_names, full_name_overrides) = join(
+        config::load_json_config::<DoctorNamesConfig>(env, config::CONFIG_DOCTOR_NAMES),
+        config::load_json_config::<Vec<String>>(env, config::CONFIG_FULL_NAME_OVERRIDES),
+    )
+    .await;
+
+    NameConfig {
+        doctor_names,
+        full_name_overrides: FullNameOverrides::from(full_name_overrides),
+    }
+}
+
+/// Pseudonyms are the trailing all-digit token of the upstream name
+fn extract_pseudonym(api_name: &str) -> Option<String> {
+    let candidate = api_name.split_whitespace().last()?;
+    if candidate.chars().all(|c| c.is_ascii_digit()) {
+        Some(candidate.to_string())
+    } else {
+        None
+    }
+}
+
+/// Drop a trailing `" <pseudonym>"` or `"(<pseudonym>)"` from a configured name
+fn strip_pseudonym(name: &str, pseudonym: &str) -> String {
+    if let Some(rest) = name.strip_suffix(pseudonym) {
+        if rest.ends_with(char::is_whitespace) {
+            return rest.trim().to_string();
+        }
+    }
+    if let Some(rest) = name.strip_suffix(&format!("({})", pseudonym)) {
+        return rest.trim().to_string();
+    }
+    name.trim().to_string()
+}
+
+fn append_pseudonym(name: &str, api_name: &str) -> String {
+    let trimmed = name.trim();
+    let pseudonym = extract_pseudonym(api_name);
+
+    if trimmed.is_empty() {
+        return pseudonym.unwrap_or_default();
+    }
+
+    match pseudonym {
+        None => trimmed.to_string(),
+        Some(pseudonym)
+            if trimmed == pseudonym
+                || trimmed.ends_with(&format!(" {}", pseudonym))
+                || trimmed.ends_with(&format!("({})", pseudonym)) =>
+        {
+            trimmed.to_string()
+        }
+        Some(pseudonym) => format!("{} {}", trimmed, pseudonym),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name_config(doctor_names: &str) -> NameConfig {
+        NameConfig {
+            doctor_names: serde_json::from_str(doctor_names).expect("valid doctor-names.json"),
+            full_name_overrides: FullNameOverrides::default(),
+        }
+    }
+
+    #[test]
+    fn shared_surname_takes_the_first_entry_in_file_order() {
+        // Sorted keys would put "Luca Rossi" first; the frontend walks file order
+        let config = name_config(
+            r#"{ "names": { "Rossi Mario": "Mario Rossi", "Luca Rossi": "Luca Rossi" } }"#,
+        );
+
+        assert_eq!(
+            config.display_name("900", "Giovanni Rossi").name,
+            "Mario Rossi"
+        );
+    }
+
+    #[test]
+    fn integer_keys_are_searched_first_like_object_entries() {
+        let config = name_config(
+            r#"{ "names": { "verdi": "Anna Verdi", "200": "Paolo Verdi", "100": "Marco Verdi" } }"#,
+        );
+
+        assert_eq!(config.display_name("900", "G. Verdi").name, "Marco Verdi");
+    }
+
+    #[test]
+    fn exact_and_case_insensitive_keys_win_over_surnames() {
+        let config = name_config(
+            r#"{ "names": { "Rossi Mario": "Mario Rossi", "42": "Dott.ssa Bianchi", "LUCA ROSSI": "Luca Rossi" } }"#,
+        );
+
+        assert_eq!(
+            config.display_name("42", "Anna Bianchi").name,
+            "Dott.ssa Bianchi"
+        );
+        assert_eq!(config.display_name("7", "luca rossi").name, "Luca Rossi");
+    }
+
+    #[test]
+    fn js_array_index_matches_javascript_rules() {
+        assert_eq!(js_array_index("0"), Some(0));
+        assert_eq!(js_array_index("123"), Some(123));
+        assert_eq!(js_array_index("4294967294"), Some(4294967294));
+        for key in ["", "01", "-1", "1.5", "4294967295", "12a"] {
+            assert_eq!(js_array_index(key), None, "{}", key);
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use chrono::{Datelike, NaiveDate, Utc};
+use serde::Serialize;
+use worker::*;
+
+use crate::{
+    build_success_response, error_response, get_config, range, response_ttl, Config, MonthShifts,
+    Person, ScheduleVersion, ShiftCell, UpstreamFailure,
+};
+
+/// One assignment in a person's flat timeline
+#[derive(Serialize, Clone)]
+pub struct PersonShift {
+    pub date: String,
+    pub code: String,
+    pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<String>,
+    #[serde(rename = "durationMinutes", skip_serializing_if = "Option::is_none")]
+    pub duration_minutes: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct PersonShiftsResponse {
+    pub person: Person,
+    pub from: String,
+    pub to: String,
+    pub shifts: Vec<PersonShift>,
+}
+
+/// API endpoint: one person's shifts across an arbitrary window
+pub async fn handle_person_shifts(
+    req: Request,
+    ctx: RouteContext<Context>,
+    person_id: String,
+) -> Result<Response> {
+    let config = match get_config(&ctx.env) {
+        Ok(cfg) => cfg,
+        Err(e) => return error_response("CONFIG_ERROR", &e.to_string(), 500),
+    };
+
+    let person_id = person_id.trim().to_string();
+    if person_id.is_empty() {
+        return error_response("MISSING_PARAM", "Missing required parameter: id", 400);
+    }
+
+    // Defaults to the current month when no window is given
+    let url = req.url()?;
+    let (start, end) = match range::parse_range_query(&url) {
+        Some(Ok(bounds)) => bounds,
+        Some(Err(message)) => return error_response("INVALID_RANGE", &message, 400),
+        None => current_month_bounds(),
+    };
+
+    let (person, shifts, cache_status) =
+        match load_person_shifts(&ctx, &config, &person_id, start, end).await {
+            Ok(Some(result)) => result,
+            Ok(None) => {
+                return error_response(
+                    "PERSON_NOT_FOUND",
+                    &format!(
+                        "No shifts found for person {} in the requested window",
+                        person_id
+                    ),
+                    404,
+                )
+            }
+            Err(failure) => return failure.into_response(),
+        };
+
+    let response = PersonShiftsResponse {
+        person,
+        from: start.format("%Y-%m-%d").to_string(),
+        to: end.format("%Y-%m-%d").to_string(),
+        shifts,
+    };
+
+    let json = serde_json::to_string(&response)?;
+    let if_none_match = req.headers().get("If-None-Match")?;
+    build_success_response(
+        json,
+        response_ttl(&config, cache_status),
+        cache_status,
+        if_none_match.as_deref(),
+    )
+}
+
+pub fn current_month_bounds() -> (NaiveDate, NaiveDate) {
+    let today = Utc::now().date_naive();
+    let start = today.with_day(1).unwrap_or(today);
+    let end = start
+        .checked_add_months(chrono::Months::new(1))
+        .and_then(|next| next.pred_opt())
+        .unwrap_or(today);
+    (start, end)
+}
+
+/// Load every month touched by the window and flatten the person's cells.
+/// Returns `None` when the person does not appear in any of those months.
+pub async fn load_person_shifts(
+    ctx: &RouteContext<Context>,
+    config: &Config,
+    person_id: &str,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> std::result::Result<Option<(Person, Vec<PersonShift>, &'static str)>, UpstreamFailure> {
+    let months = range::months_in_range(start, end);
+    let results = range::load_months(ctx, config, &months, ScheduleVersion::Live).await;
+
+    let mut person: Option<Person> = None;
+    let mut shifts: Vec<PersonShift> = Vec::new();
+    let mut all_hits = true;
+    let mut any_stale = false;
+
+    for result in results {
+        let (month_shifts, status) = result?;
+        all_hits &= status == "HIT";
+        any_stale |= status == "STALE";
+
+        if let Some((found, month_person_shifts)) =
+            collect_person_shifts(&month_shifts, person_id, start, end)
+        {
+            person.get_or_insert(found);
+            shifts.extend(month_person_shifts);
+        }
+    }
+
+    shifts.sort_by(|a, b| {
+        a.date
+            .cmp(&b.date)
+            .then_with(|| a.start.cmp(&b.start))
+            .then_with(|| a.code.cmp(&b.code))
+    });
+
+    let cache_status = if any_stale {
+        "STALE"
+    } else if all_hits {
+        "HIT"
+    } else {
+        "MISS"
+    };
+    Ok(person.map(|person| (person, shifts, cache_status)))
+}
+
+/// Flatten one month's row for `person_id`, keeping only days inside [start, end]
+pub fn collect_person_shifts(
+    month_shifts: &MonthShifts,
+    person_id: &str,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Option<(Person, Vec<PersonShift>)> {
+    let person_idx = month_shifts
+        .people
+        .iter()
+        .position(|person| person.id == person_id)?;
+    let month_start =
+        NaiveDate::parse_from_str(&format!("{}-01", month_shifts.ym), "%Y-%m-%d").ok()?;
+
+    let mut shifts = Vec::new();
+    for (day_idx, cell) in month_shifts.rows.get(person_idx)?.iter().enumerate() {
+        let Some(entries) = cell else {
+            continue;
+        };
+        let Some(date) = month_start.checked_add_days(chrono::Days::new(day_idx as u64)) else {
+            continue;
+        };
+        if date < start || date > end {
+            continue;
+        }
+
+        for entry in entries {
+            let code = entry.code().to_string();
+            let label = month_shifts
+                .shift_names
+                .get(&code)
+                .cloned()
+                .unwrap_or_else(|| code.clone());
+            let (start_time, end_time, duration_minutes) = match entry {
+                ShiftCell::Timed(timed) => (
+                    timed.start.clone(),
+                    timed.end.clone(),
+                    timed.duration_minutes,
+                ),
+                ShiftCell::Code(_) => (None, None, None),
+            };
+
+            shifts.push(PersonShift {
+                date: date.format("%Y-%m-%d").to_string(),
+                code,
+                label,
+                start: start_time,
+                end: end_time,
+                duration_minutes,
+            });
+        }
+    }
+
+    Some((month_shifts.people[person_idx].clone(), shifts))
+}
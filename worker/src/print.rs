@@ -0,0 +1,403 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+use futures::future::join;
+use std::collections::{HashMap, HashSet};
+use worker::*;
+
+use crate::range::{self, RangeShifts};
+use crate::stats::month_dates;
+use crate::styles::{self, ShiftStyles};
+use crate::{
+    build_file_response, check_version_access, error_response, get_config, groups, is_valid_ym,
+    query_param, response_ttl, restrict_to_version_audience, unknown_group_response, MonthShifts,
+    ScheduleVersion, ShiftDetail,
+};
+
+const MONTH_NAMES: [&str; 12] = [
+    "gennaio",
+    "febbraio",
+    "marzo",
+    "aprile",
+    "maggio",
+    "giugno",
+    "luglio",
+    "agosto",
+    "settembre",
+    "ottobre",
+    "novembre",
+    "dicembre",
+];
+
+// Same tints as the grid (bg-blue-50 / bg-red-50)
+const WEEKEND_BACKGROUND: &str = "#eff6ff";
+const HOLIDAY_BACKGROUND: &str = "#fef2f2";
+
+const PRINT_CSS: &str = "\
+@page { size: A4 landscape; margin: 8mm; }
+* { box-sizing: border-box; }
+body { font-family: Arial, Helvetica, sans-serif; font-size: 8pt; color: #111827; margin: 0; \
+-webkit-print-color-adjust: exact; print-color-adjust: exact; }
+h1 { font-size: 13pt; margin: 0 0 4mm; }
+table { border-collapse: collapse; width: 100%; }
+table.grid { table-layout: fixed; }
+thead { display: table-header-group; }
+tr { break-inside: avoid; page-break-inside: avoid; }
+th, td { border: 1px solid #9ca3af; padding: 1px 2px; text-align: center; vertical-align: middle; }
+th.name, td.name { text-align: left; white-space: nowrap; overflow: hidden; text-overflow: ellipsis; }
+thead th { font-size: 7pt; }
+tr.group th { text-align: left; background: #e5e7eb; font-size: 8pt; }
+.chip { display: block; border-radius: 2px; margin: 1px 0; padding: 0 1px; font-weight: bold; white-space: nowrap; }
+.underline { text-decoration: overline; }
+section.legend { break-inside: avoid; page-break-inside: avoid; margin-top: 5mm; }
+section.legend h2 { font-size: 10pt; margin: 0 0 2mm; }
+table.legend { width: auto; }
+table.legend td { text-align: left; padding: 1px 6px; }
+";
+
+/// API endpoint: print-ready HTML of a month (`ym`) or ISO week (`week`)
+pub async fn handle_print(req: Request, ctx: RouteContext<Context>) -> Result<Response> {
+    let config = match get_config(&ctx.env) {
+        Ok(cfg) => cfg,
+        Err(e) => return error_response("CONFIG_ERROR", &e.to_string(), 500),
+    };
+
+    let url = req.url()?;
+    let (start, end, title) = match (query_param(&url, "ym"), query_param(&url, "week")) {
+        (Some(ym), None) => match month_period(ym.trim()) {
+            Some(period) => period,
+            None => {
+                return error_response("INVALID_YM", "Invalid ym format. Expected YYYY-MM", 400)
+            }
+        },
+        (None, Some(week)) => match week_period(week.trim()) {
+            Some(period) => period,
+            None => {
+                return error_response(
+                    "INVALID_WEEK",
+                    "Invalid week format. Expected YYYY-Www",
+                    400,
+                )
+            }
+        },
+        _ => {
+            return error_response(
+                "MISSING_PARAM",
+                "Pass exactly one of ym (YYYY-MM) or week (YYYY-Www)",
+                400,
+            )
+        }
+    };
+
+    let version = match ScheduleVersion::from_query(query_param(&url, "version").as_deref()) {
+        Some(version) => version,
+        None => {
+            return error_response(
+                "INVALID_VERSION",
+                "Invalid version value. Expected live, draft or published",
+                400,
+            )
+        }
+    };
+    if let Some(denied) = check_version_access(&req, &ctx, version) {
+        return denied;
+    }
+    let group = query_param(&url, "group")
+        .map(|group| group.trim().to_string())
+        .filter(|group| !group.is_empty());
+
+    let months = range::months_in_range(start, end);
+    if !months.iter().all(|ym| is_valid_ym(ym)) {
+        return error_response("INVALID_RANGE", "Period is out of the supported range", 400);
+    }
+    let (results, style_config) = join(
+        range::load_months(&ctx, &config, &months, version),
+        styles::load_style_config(&ctx.env),
+    )
+    .await;
+
+    let mut month_map: HashMap<String, MonthShifts> = HashMap::new();
+    let mut statuses: HashSet<&'static str> = HashSet::new();
+    let mut group_found = false;
+    for (ym, result) in months.iter().zip(results) {
+        match result {
+            Ok((mut month_shifts, status)) => {
+                statuses.insert(status);
+                if let Some(group) = &group {
+                    group_found |= groups::retain_group(&mut month_shifts, group);
+                }
+                month_map.insert(ym.clone(), month_shifts.with_detail(ShiftDetail::Codes));
+            }
+            Err(failure) => return failure.into_response(),
+        }
+    }
+    if let Some(group) = group.as_deref().filter(|_| !group_found) {
+        return unknown_group_response(group);
+    }
+
+    let schedule = range::merge_months(start, end, &months, &month_map, HashMap::new());
+    let styles = style_config.resolve(
+        &schedule.rows,
+        &schedule.days,
+        &schedule.codes,
+        &schedule.upstream_colors,
+    );
+    let html = render_html(&title, &schedule, &styles);
+
+    let cache_status = if statuses.contains("STALE") {
+        "STALE"
+    } else if statuses.len() == 1 && statuses.contains("HIT") {
+        "HIT"
+    } else {
+        "MISS"
+    };
+
+    let if_none_match = req.headers().get("If-None-Match")?;
+    let mut response = build_file_response(
+        html.into_bytes(),
+        "text/html; charset=utf-8",
+        "inline",
+        response_ttl(&config, cache_status),
+        cache_status,
+        if_none_match.as_deref(),
+    )?;
+    // Config-provided colours end up in style attributes; nothing else may load
+    response.headers_mut().set(
+        "Content-Security-Policy",
+        "default-src 'none'; style-src 'unsafe-inline'",
+    )?;
+    restrict_to_version_audience(response, version, &req)
+}
+
+fn month_period(ym: &str) -> Option<(NaiveDate, NaiveDate, String)> {
+    if !is_valid_ym(ym) {
+        return None;
+    }
+    let (start, end) = month_dates(ym)?;
+    let title = format!(
+        "Turni {} {}",
+        MONTH_NAMES[start.month0() as usize],
+        start.year()
+    );
+    Some((start, end, title))
+}
+
+/// `2025-W41` (or `2025W41`) to its Monday..Sunday
+fn week_period(week: &str) -> Option<(NaiveDate, NaiveDate, String)> {
+    let (year, number) = week.split_once(['W', 'w'])?;
+    let year: i32 = year.trim_end_matches('-').parse().ok()?;
+    let number: u32 = number.parse().ok()?;
+    let start = NaiveDate::from_isoywd_opt(year, number, Weekday::Mon)?;
+    let end = NaiveDate::from_isoywd_opt(year, number, Weekday::Sun)?;
+    let title = format!(
+        "Turni settimana {}-W{:02} ({} – {})",
+        year,
+        number,
+        start.format("%d/%m"),
+        end.format("%d/%m/%Y")
+    );
+    Some((start, end, title))
+}
+
+fn render_html(title: &str, schedule: &RangeShifts, styles: &ShiftStyles) -> String {
+    let underlined: HashSet<(usize, usize, usize)> = styles
+        .cells
+        .iter()
+        .filter(|cell| cell.underline)
+        .map(|cell| (cell.row, cell.day, cell.entry))
+        .collect();
+    let group_labels: HashMap<&str, &str> = schedule
+        .groups
+        .iter()
+        .map(|group| (group.id.as_str(), group.label.as_str()))
+        .collect();
+    let column_count = schedule.days.len() + 1;
+    // Weeks get roomier columns; a month needs every millimetre
+    let name_width = if schedule.days.len() > 7 {
+        "11%"
+    } else {
+        "16%"
+    };
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"it\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape_html(title)));
+    html.push_str(&format!("<style>\n{}</style>\n", PRINT_CSS));
+    html.push_str("</head>\n<body>\n");
+    html.push_str(&format!("<h1>{}</h1>\n", escape_html(title)));
+
+    html.push_str("<table class=\"grid\">\n<colgroup>");
+    html.push_str(&format!("<col style=\"width: {}\">", name_width));
+    html.push_str("</colgroup>\n<thead><tr><th class=\"name\">Nome</th>");
+    for day in &schedule.days {
+        let tooltip = match (&day.holiday_name, &day.closure) {
+            (Some(holiday), _) => format!(" title=\"{}\"", escape_html(holiday)),
+            (None, Some(closure)) => format!(" title=\"{}\"", escape_html(closure)),
+            (None, None) => String::new(),
+        };
+        html.push_str(&format!(
+            "<th{}{}>{}</th>",
+            day_background(day.weekday, day.holiday || day.closure.is_some()),
+            tooltip,
+            escape_html(&day.short_label())
+        ));
+    }
+    html.push_str("</tr></thead>\n<tbody>\n");
+
+    let mut current_group: Option<&str> = None;
+    for (row_idx, (person, row)) in schedule.people.iter().zip(&schedule.rows).enumerate() {
+        if let Some(group) = person.group.as_deref() {
+            if current_group != Some(group) {
+                current_group = Some(group);
+                html.push_str(&format!(
+                    "<tr class=\"group\"><th colspan=\"{}\">{}</th></tr>\n",
+                    column_count,
+                    escape_html(group_labels.get(group).copied().unwrap_or(group))
+                ));
+            }
+        } else if current_group.is_some() {
+            current_group = None;
+            html.push_str(&format!(
+                "<tr class=\"group\"><th colspan=\"{}\">Altri</th></tr>\n",
+                column_count
+            ));
+        }
+
+        html.push_str(&format!(
+            "<tr><td class=\"name\">{}</td>",
+            escape_html(person.label())
+        ));
+        for (day_idx, cell) in row.iter().enumerate() {
+            let day = &schedule.days[day_idx];
+            html.push_str(&format!(
+                "<td{}>",
+                day_background(day.weekday, day.holiday || day.closure.is_some())
+            ));
+            for (entry_idx, entry) in cell.iter().flatten().enumerate() {
+                let code = entry.code();
+                let class = if underlined.contains(&(row_idx, day_idx, entry_idx)) {
+                    "chip underline"
+                } else {
+                    "chip"
+                };
+                html.push_str(&format!(
+                    "<span class=\"{}\"{}>{}</span>",
+                    class,
+                    chip_style(styles, code),
+                    escape_html(code)
+                ));
+            }
+            html.push_str("</td>");
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>\n");
+
+    if !schedule.codes.is_empty() {
+        html.push_str("<section class=\"legend\">\n<h2>Legenda</h2>\n<table class=\"legend\">\n");
+        for code in &schedule.codes {
+            let name = schedule
+                .shift_names
+                .get(code)
+                .map(String::as_str)
+                .unwrap_or_default();
+            html.push_str(&format!(
+                "<tr><td><span class=\"chip\"{}>{}</span></td><td>{}</td></tr>\n",
+                chip_style(styles, code),
+                escape_html(code),
+                escape_html(name)
+            ));
+        }
+        html.push_str("</table>\n</section>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn chip_style(styles: &ShiftStyles, code: &str) -> String {
+    match styles.codes.get(code) {
+        Some(style) => format!(
+            " style=\"background-color: {}; color: {}\"",
+            escape_html(&style.background),
+            escape_html(&style.text)
+        ),
+        None => String::new(),
+    }
+}
+
+fn day_background(weekday: u32, holiday: bool) -> String {
+    let background = if holiday {
+        HOLIDAY_BACKGROUND
+    } else if weekday == 0 || weekday == 6 {
+        WEEKEND_BACKGROUND
+    } else {
+        return String::new();
+    };
+    format!(" style=\"background-color: {}\"", background)
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn week_53_runs_into_the_next_year() {
+        let (start, end, title) = week_period("2020-W53").unwrap();
+
+        assert_eq!((start, end), (date("2020-12-28"), date("2021-01-03")));
+        assert_eq!(title, "Turni settimana 2020-W53 (28/12 – 03/01/2021)");
+        // 2021 only has 52 ISO weeks
+        assert!(week_period("2021-W53").is_none());
+    }
+
+    #[test]
+    fn week_01_can_start_in_the_previous_year() {
+        let (start, end, title) = week_period("2025-W01").unwrap();
+
+        assert_eq!((start, end), (date("2024-12-30"), date("2025-01-05")));
+        assert_eq!(title, "Turni settimana 2025-W01 (30/12 – 05/01/2025)");
+    }
+
+    #[test]
+    fn week_formats() {
+        let expected = Some((date("2025-10-06"), date("2025-10-12")));
+        for week in ["2025-W41", "2025W41", "2025-w41"] {
+            assert_eq!(
+                week_period(week).map(|(start, end, _)| (start, end)),
+                expected,
+                "{}",
+                week
+            );
+        }
+        for invalid in ["2025-41", "2025-W00", "2025-W", "W41", "2025-W4x"] {
+            assert!(week_period(invalid).is_none(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn month_period_title() {
+        let (start, end, title) = month_period("2024-02").unwrap();
+
+        assert_eq!((start, end), (date("2024-02-01"), date("2024-02-29")));
+        assert_eq!(title, "Turni febbraio 2024");
+        assert!(month_period("2024-13").is_none());
+    }
+}
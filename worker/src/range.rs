@@ -0,0 +1,574 @@
+use chrono::{Datelike, NaiveDate};
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use worker::*;
+
+use crate::groups::{self, PersonGroup};
+use crate::holidays::{DayInfo, HolidayCalendar};
+use crate::styles::{StyleConfig, Styled};
+use crate::{
+    build_success_response, is_valid_ym, load_month_shifts, offset_ym, query_param, response_ttl,
+    unknown_group_response, Config, MonthShifts, Person, ScheduleVersion, ShiftCell, ShiftDetail,
+    UpstreamFailure,
+};
+
+pub const MAX_RANGE_DAYS: i64 = 366;
+/// Months loaded at once: each miss is an upstream fetch, and Workers allow 6 open connections
+const MONTH_LOAD_CONCURRENCY: usize = 4;
+
+// Range variant of the MonthShifts contract: the day axis is `dates` instead of a single month
+#[derive(Serialize)]
+pub struct RangeShifts {
+    pub from: String,
+    pub to: String,
+    pub dates: Vec<String>,
+    /// Calendar metadata for each entry of `dates`
+    pub days: Vec<DayInfo>,
+    pub people: Vec<Person>,
+    pub rows: Vec<Vec<Option<Vec<ShiftCell>>>>,
+    pub codes: Vec<String>,
+    #[serde(rename = "shiftNames")]
+    pub shift_names: BTreeMap<String, String>,
+    #[serde(rename = "upstreamColors", skip_serializing_if = "BTreeMap::is_empty")]
+    pub upstream_colors: BTreeMap<String, String>,
+    /// Overnight shifts from the day before `from`, per person, as in the month contract
+    #[serde(rename = "carryIn", skip_serializing_if = "Vec::is_empty")]
+    pub carry_in: Vec<Option<Vec<ShiftCell>>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<PersonGroup>,
+}
+
+/// Parse `from`/`to` (YYYY-MM-DD) or `from_ym`/`to_ym` (YYYY-MM).
+/// Returns `None` when the request is not a range query.
+pub fn parse_range_query(url: &Url) -> Option<std::result::Result<(NaiveDate, NaiveDate), String>> {
+    let from = query_param(url, "from");
+    let to = query_param(url, "to");
+    let from_ym = query_param(url, "from_ym");
+    let to_ym = query_param(url, "to_ym");
+
+    if from.is_none() && to.is_none() && from_ym.is_none() && to_ym.is_none() {
+        return None;
+    }
+
+    Some(resolve_range(from, to, from_ym, to_ym))
+}
+
+fn resolve_range(
+    from: Option<String>,
+    to: Option<String>,
+    from_ym: Option<String>,
+    to_ym: Option<String>,
+) -> std::result::Result<(NaiveDate, NaiveDate), String> {
+    let (start, end) =
+        match (from, to, from_ym, to_ym) {
+            (Some(from), Some(to), None, None) => (parse_date(&from)?, parse_date(&to)?),
+            (None, None, Some(from_ym), Some(to_ym)) => {
+                let start = parse_date(&format!("{}-01", from_ym))
+                    .map_err(|_| "Invalid from_ym format. Expected YYYY-MM".to_string())?;
+                let end_month = parse_date(&format!("{}-01", to_ym))
+                    .map_err(|_| "Invalid to_ym format. Expected YYYY-MM".to_string())?;
+                let end = offset_ym(&end_month.format("%Y-%m").to_string(), 1)
+                    .and_then(|next| parse_date(&format!("{}-01", next)).ok())
+                    .and_then(|next| next.pred_opt())
+                    .ok_or_else(|| "Invalid to_ym value".to_string())?;
+                (start, end)
+            }
+            _ => return Err(
+                "Range queries need both from and to (YYYY-MM-DD) or from_ym and to_ym (YYYY-MM)"
+                    .to_string(),
+            ),
+        };
+
+    if end < start {
+        return Err("Range end must not be before its start".to_string());
+    }
+
+    let span_days = end.signed_duration_since(start).num_days() + 1;
+    if span_days > MAX_RANGE_DAYS {
+        return Err(format!(
+            "Range cannot exceed {} days (requested {})",
+            MAX_RANGE_DAYS, span_days
+        ));
+    }
+
+    Ok((start, end))
+}
+
+fn parse_date(value: &str) -> std::result::Result<NaiveDate, String> {
+    let date = NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date {}. Expected YYYY-MM-DD", value))?;
+
+    if !is_valid_ym(&date.format("%Y-%m").to_string()) {
+        return Err(format!("Date {} is out of the supported range", value));
+    }
+
+    Ok(date)
+}
+
+/// Months (YYYY-MM) touched by an inclusive date range
+pub fn months_in_range(start: NaiveDate, end: NaiveDate) -> Vec<String> {
+    let last = end.format("%Y-%m").to_string();
+    let mut months = vec![start.format("%Y-%m").to_string()];
+
+    while let Some(next) = months
+        .last()
+        .filter(|ym| **ym != last)
+        .and_then(|ym| offset_ym(ym, 1))
+    {
+        months.push(next);
+    }
+
+    months
+}
+
+/// Query options `/api/shifts` shares with its range form
+pub struct RangeOptions<'a> {
+    pub detail: ShiftDetail,
+    pub version: ScheduleVersion,
+    pub group: Option<&'a str>,
+    pub style_config: Option<&'a StyleConfig>,
+    pub if_none_match: Option<&'a str>,
+}
+
+/// Load `months` in order, a few at a time
+pub async fn load_months(
+    ctx: &RouteContext<Context>,
+    config: &Config,
+    months: &[String],
+    version: ScheduleVersion,
+) -> Vec<std::result::Result<(MonthShifts, &'static str), UpstreamFailure>> {
+    stream::iter(months)
+        .map(|ym| load_month_shifts(ctx, config, ym, version))
+        .buffered(MONTH_LOAD_CONCURRENCY)
+        .collect()
+        .await
+}
+
+/// Serve `/api/shifts?from=...&to=...` by stitching per-month cache entries together
+pub async fn handle_shifts_range(
+    ctx: &RouteContext<Context>,
+    config: &Config,
+    start: NaiveDate,
+    end: NaiveDate,
+    options: RangeOptions<'_>,
+) -> Result<Response> {
+    let RangeOptions {
+        detail,
+        version,
+        group,
+        style_config,
+        if_none_match,
+    } = options;
+    let months = months_in_range(start, end);
+
+    let results = load_months(ctx, config, &months, version).await;
+
+    let mut month_map: HashMap<String, MonthShifts> = HashMap::new();
+    let mut statuses: HashSet<&'static str> = HashSet::new();
+    let mut group_found = false;
+    let mut carry_in: HashMap<String, Vec<ShiftCell>> = HashMap::new();
+    for (ym, result) in months.iter().zip(results) {
+        match result {
+            Ok((mut month_shifts, status)) => {
+                statuses.insert(status);
+                if let Some(group) = group {
+                    group_found |= groups::retain_group(&mut month_shifts, group);
+                }
+                // Needs the timed cells, so it runs before the detail projection
+                if ym == &months[0] {
+                    carry_in = collect_carry_in(&month_shifts, start, detail);
+                }
+                month_map.insert(ym.clone(), month_shifts.with_detail(detail));
+            }
+            Err(failure) => return failure.into_response(),
+        }
+    }
+    if let Some(group) = group.filter(|_| !group_found) {
+        return unknown_group_response(group);
+    }
+
+    let range_shifts = merge_months(start, end, &months, &month_map, carry_in);
+
+    // Any stale month makes the whole range stale
+    let cache_status = if statuses.contains("STALE") {
+        "STALE"
+    } else {
+        match (statuses.contains("HIT"), statuses.contains("MISS")) {
+            (true, true) => "PARTIAL",
+            (true, false) => "HIT",
+            _ => "MISS",
+        }
+    };
+
+    let json = match style_config {
+        Some(style_config) => {
+            let styles = style_config.resolve(
+                &range_shifts.rows,
+                &range_shifts.days,
+                &range_shifts.codes,
+                &range_shifts.upstream_colors,
+            );
+            serde_json::to_string(&Styled {
+                body: range_shifts,
+                styles,
+            })?
+        }
+        None => serde_json::to_string(&range_shifts)?,
+    };
+    build_success_response(
+        json,
+        response_ttl(config, cache_status),
+        cache_status,
+        if_none_match,
+    )
+}
+
+/// Overnight shifts from the day before `start` that run into it, by person ID.
+/// On the 1st these are the month's own carry-in; otherwise the previous day's overnight cells.
+fn collect_carry_in(
+    month_shifts: &MonthShifts,
+    start: NaiveDate,
+    detail: ShiftDetail,
+) -> HashMap<String, Vec<ShiftCell>> {
+    month_shifts
+        .people
+        .iter()
+        .enumerate()
+        .filter_map(|(person_idx, person)| {
+            let cells: Vec<ShiftCell> = match start.day0().checked_sub(1) {
+                None => month_shifts.carry_in.get(person_idx)?.clone()?,
+                Some(previous_day) => month_shifts
+                    .rows
+                    .get(person_idx)?
+                    .get(previous_day as usize)?
+                    .iter()
+                    .flatten()
+                    .filter(|cell| matches!(cell, ShiftCell::Timed(timed) if timed.overnight))
+                    .cloned()
+                    .collect(),
+            };
+            if cells.is_empty() {
+                return None;
+            }
+            let cells = cells
+                .into_iter()
+                .map(|cell| cell.with_detail(detail))
+                .collect();
+            Some((person.id.clone(), cells))
+        })
+        .collect()
+}
+
+/// Stitch the loaded months into the range contract. `carry_in` is keyed by person ID.
+pub fn merge_months(
+    start: NaiveDate,
+    end: NaiveDate,
+    months: &[String],
+    month_map: &HashMap<String, MonthShifts>,
+    mut carry_in: HashMap<String, Vec<ShiftCell>>,
+) -> RangeShifts {
+    // Union of people across months, ordered by group like the single-month contract
+    let mut people_map: HashMap<String, Person> = HashMap::new();
+    let mut month_names: HashMap<String, String> = HashMap::new();
+    let mut month_colors: HashMap<String, String> = HashMap::new();
+
+    for ym in months {
+        if let Some(month_shifts) = month_map.get(ym) {
+            for person in &month_shifts.people {
+                people_map
+                    .entry(person.id.clone())
+                    .or_insert_with(|| person.clone());
+            }
+            for (code, name) in &month_shifts.shift_names {
+                month_names
+                    .entry(code.clone())
+                    .or_insert_with(|| name.clone());
+            }
+            for (code, color) in &month_shifts.upstream_colors {
+                month_colors
+                    .entry(code.clone())
+                    .or_insert_with(|| color.clone());
+            }
+        }
+    }
+
+    let groups = groups::merge_groups(months.iter().filter_map(|ym| month_map.get(ym)));
+    let mut people: Vec<Person> = people_map.into_values().collect();
+    groups::sort_people(&mut people, &groups);
+
+    // person_id -> row index within each month
+    let month_indices: HashMap<&str, HashMap<&str, usize>> = month_map
+        .iter()
+        .map(|(ym, month_shifts)| {
+            let indices = month_shifts
+                .people
+                .iter()
+                .enumerate()
+                .map(|(i, p)| (p.id.as_str(), i))
+                .collect();
+            (ym.as_str(), indices)
+        })
+        .collect();
+
+    let mut dates: Vec<NaiveDate> = Vec::new();
+    let mut cursor = start;
+    while cursor <= end {
+        dates.push(cursor);
+        match cursor.succ_opt() {
+            Some(next) => cursor = next,
+            None => break,
+        }
+    }
+
+    let mut carry_in: Vec<Option<Vec<ShiftCell>>> = people
+        .iter()
+        .map(|person| carry_in.remove(&person.id))
+        .collect();
+    if carry_in.iter().all(Option::is_none) {
+        carry_in.clear();
+    }
+
+    // Months cached before `days` existed fall back to the built-in calendar
+    let days: Vec<DayInfo> = dates
+        .iter()
+        .map(|date| {
+            month_map
+                .get(&date.format("%Y-%m").to_string())
+                .and_then(|month_shifts| month_shifts.days.get(date.day0() as usize))
+                .cloned()
+                .unwrap_or_else(|| HolidayCalendar::default().day_info(*date))
+        })
+        .collect();
+
+    let rows: Vec<Vec<Option<Vec<ShiftCell>>>> = people
+        .iter()
+        .map(|person| {
+            dates
+                .iter()
+                .map(|date| {
+                    let ym = date.format("%Y-%m").to_string();
+                    let month_shifts = month_map.get(&ym)?;
+                    let person_idx = *month_indices.get(ym.as_str())?.get(person.id.as_str())?;
+                    month_shifts
+                        .rows
+                        .get(person_idx)?
+                        .get(date.day0() as usize)?
+                        .clone()
+                })
+                .collect()
+        })
+        .collect();
+
+    // Only report codes that actually appear inside the requested window
+    let codes: HashSet<String> = rows
+        .iter()
+        .flatten()
+        .flatten()
+        .flatten()
+        .map(|cell| cell.code().to_string())
+        .filter(|code| !code.is_empty())
+        .collect();
+    let shift_names: BTreeMap<String, String> = month_names
+        .into_iter()
+        .filter(|(code, _)| codes.contains(code))
+        .collect();
+    let upstream_colors: BTreeMap<String, String> = month_colors
+        .into_iter()
+        .filter(|(code, _)| codes.contains(code))
+        .collect();
+
+    let mut codes: Vec<String> = codes.into_iter().collect();
+    codes.sort();
+
+    RangeShifts {
+        from: start.format("%Y-%m-%d").to_string(),
+        to: end.format("%Y-%m-%d").to_string(),
+        dates: dates
+            .iter()
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .collect(),
+        days,
+        people,
+        rows,
+        codes,
+        shift_names,
+        upstream_colors,
+        carry_in,
+        groups,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(query: &str) -> Option<std::result::Result<(NaiveDate, NaiveDate), String>> {
+        let url = Url::parse(&format!("https://example.com/api/shifts?{}", query)).unwrap();
+        parse_range_query(&url)
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    /// A month with `cells` as (person id, day, code)
+    fn month(ym: &str, people: &[(&str, &str)], cells: &[(&str, u32, &str)]) -> MonthShifts {
+        let first = date(&format!("{}-01", ym));
+        let days = (28..=31)
+            .rev()
+            .find(|day| first.with_day(*day).is_some())
+            .unwrap();
+        let rows: Vec<Vec<Option<Vec<&str>>>> = people
+            .iter()
+            .map(|(id, _)| {
+                (1..=days)
+                    .map(|day| {
+                        let codes: Vec<&str> = cells
+                            .iter()
+                            .filter(|(person, d, _)| person == id && *d == day)
+                            .map(|(_, _, code)| *code)
+                            .collect();
+                        (!codes.is_empty()).then_some(codes)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        serde_json::from_value(json!({
+            "ym": ym,
+            "people": people
+                .iter()
+                .map(|(id, name)| json!({ "id": id, "name": name }))
+                .collect::<Vec<_>>(),
+            "rows": rows,
+            "codes": [],
+            "shiftNames": { "M": "Mattino", "N": "Notte", "P": "Pomeriggio" }
+        }))
+        .expect("valid month")
+    }
+
+    fn codes(cell: &Option<Vec<ShiftCell>>) -> Vec<&str> {
+        cell.iter().flatten().map(ShiftCell::code).collect()
+    }
+
+    #[test]
+    fn plain_month_query_is_not_a_range() {
+        assert!(parse("ym=2025-01").is_none());
+    }
+
+    #[test]
+    fn date_range_is_inclusive() {
+        assert_eq!(
+            parse("from=2025-01-30&to=2025-02-02"),
+            Some(Ok((date("2025-01-30"), date("2025-02-02"))))
+        );
+    }
+
+    #[test]
+    fn month_range_ends_on_the_last_day() {
+        assert_eq!(
+            parse("from_ym=2024-12&to_ym=2025-02"),
+            Some(Ok((date("2024-12-01"), date("2025-02-28"))))
+        );
+    }
+
+    #[test]
+    fn invalid_ranges_are_rejected() {
+        for query in [
+            "from=2025-01-01",
+            "from=2025-01-01&to_ym=2025-02",
+            "from=2025-02-01&to=2025-01-31",
+            "from=2025-13-01&to=2025-12-31",
+            "from=1999-12-01&to=2000-01-31",
+            "from=2024-01-01&to=2025-01-01",
+        ] {
+            assert!(matches!(parse(query), Some(Err(_))), "{}", query);
+        }
+        // A leap year is exactly MAX_RANGE_DAYS long
+        assert!(matches!(
+            parse("from=2024-01-01&to=2024-12-31"),
+            Some(Ok(_))
+        ));
+    }
+
+    #[test]
+    fn months_in_range_crosses_years() {
+        assert_eq!(
+            months_in_range(date("2024-11-15"), date("2025-02-01")),
+            ["2024-11", "2024-12", "2025-01", "2025-02"]
+        );
+    }
+
+    #[test]
+    fn merge_months_stitches_days_across_months() {
+        let january = month(
+            "2025-01",
+            &[("101", "Giulia Bianchi")],
+            &[("101", 30, "M"), ("101", 31, "N")],
+        );
+        let february = month(
+            "2025-02",
+            &[("101", "Giulia Bianchi"), ("102", "Anna Rossi")],
+            &[("101", 2, "P"), ("102", 1, "M"), ("102", 20, "N")],
+        );
+        let months = months_in_range(date("2025-01-30"), date("2025-02-02"));
+        let month_map: HashMap<String, MonthShifts> = [
+            ("2025-01".to_string(), january),
+            ("2025-02".to_string(), february),
+        ]
+        .into_iter()
+        .collect();
+
+        let merged = merge_months(
+            date("2025-01-30"),
+            date("2025-02-02"),
+            &months,
+            &month_map,
+            HashMap::new(),
+        );
+
+        assert_eq!(
+            merged.dates,
+            ["2025-01-30", "2025-01-31", "2025-02-01", "2025-02-02"]
+        );
+        assert_eq!(merged.days.len(), 4);
+        let ids: Vec<&str> = merged.people.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["102", "101"]);
+
+        let giulia: Vec<Vec<&str>> = merged.rows[1].iter().map(codes).collect();
+        assert_eq!(giulia, [vec!["M"], vec!["N"], vec![], vec!["P"]]);
+        // Missing from January: empty days, not a shifted row
+        let anna: Vec<Vec<&str>> = merged.rows[0].iter().map(codes).collect();
+        assert_eq!(anna, [vec![], vec![], vec!["M"], vec![]]);
+
+        // Only codes inside the window, and only their names
+        assert_eq!(merged.codes, ["M", "N", "P"]);
+        assert_eq!(merged.shift_names.len(), 3);
+        assert!(merged.carry_in.is_empty());
+    }
+
+    #[test]
+    fn merge_months_drops_codes_outside_the_window() {
+        let january = month(
+            "2025-01",
+            &[("101", "Giulia Bianchi")],
+            &[("101", 2, "M"), ("101", 20, "N")],
+        );
+        let months = vec!["2025-01".to_string()];
+        let month_map: HashMap<String, MonthShifts> =
+            [("2025-01".to_string(), january)].into_iter().collect();
+
+        let merged = merge_months(
+            date("2025-01-01"),
+            date("2025-01-07"),
+            &months,
+            &month_map,
+            HashMap::new(),
+        );
+
+        assert_eq!(merged.codes, ["M"]);
+        assert_eq!(merged.shift_names.keys().collect::<Vec<_>>(), ["M"]);
+    }
+}
@@ -0,0 +1,344 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use worker::*;
+
+use crate::people::{current_month_bounds, load_person_shifts, PersonShiftsResponse};
+use crate::range::MAX_RANGE_DAYS;
+use crate::stats::month_dates;
+use crate::{
+    build_cors_headers, build_success_response, error_response, error_response_with_origin,
+    extract_viewer_session, get_config, holidays, is_valid_ym, load_month_shifts, query_param,
+    resolve_session_signing_secret, response_ttl, sign_claims, verify_claims, ScheduleVersion,
+    ShiftDetail, SCHEDULER_ROLE,
+};
+
+const SHARE_TOKEN_TYPE: &str = "share";
+const DEFAULT_SHARE_DAYS: i64 = 7;
+const MAX_SHARE_DAYS: i64 = 90;
+
+/// What a share link may read: one month of the roster, or one person's shifts in a window
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ShareScope {
+    Month {
+        ym: String,
+    },
+    Person {
+        id: String,
+        from: String,
+        to: String,
+    },
+}
+
+/// Signed like a viewer session, but never accepted as one (no `vid`, explicit `typ`)
+#[derive(Serialize, Deserialize)]
+struct ShareClaims {
+    typ: String,
+    sid: String,
+    iat: i64,
+    exp: i64,
+    scope: ShareScope,
+}
+
+#[derive(Deserialize)]
+struct ShareRequest {
+    #[serde(default)]
+    ym: Option<String>,
+    #[serde(default)]
+    person: Option<String>,
+    #[serde(default)]
+    from: Option<String>,
+    #[serde(default)]
+    to: Option<String>,
+    #[serde(rename = "expiresInDays", default)]
+    expires_in_days: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct ShareResponse {
+    token: String,
+    /// Worker path that serves the shared data
+    path: String,
+    scope: ShareScope,
+    #[serde(rename = "issuedAt")]
+    issued_at: String,
+    #[serde(rename = "expiresAt")]
+    expires_at: String,
+}
+
+/// API endpoint: mint a read-only share link (scheduler session required)
+pub async fn handle_create_share(mut req: Request, ctx: RouteContext<Context>) -> Result<Response> {
+    let origin = req
+        .headers()
+        .get("Origin")?
+        .unwrap_or_else(|| "*".to_string());
+
+    match extract_viewer_session(&req, &ctx) {
+        Some(claims) if claims.has_role(SCHEDULER_ROLE) => {}
+        Some(_) => {
+            return error_response_with_origin(
+                "FORBIDDEN",
+                "Share links can only be created with a scheduler session",
+                403,
+                &origin,
+            )
+        }
+        None => {
+            return error_response_with_origin(
+                "UNAUTHORIZED",
+                "Sign in as a scheduler to create share links",
+                401,
+                &origin,
+            )
+        }
+    }
+
+    let body: ShareRequest = match req.json().await {
+        Ok(body) => body,
+        Err(_) => {
+            return error_response_with_origin("INVALID_REQUEST", "Invalid JSON", 400, &origin)
+        }
+    };
+
+    let days = body.expires_in_days.unwrap_or(DEFAULT_SHARE_DAYS);
+    if !(1..=MAX_SHARE_DAYS).contains(&days) {
+        return error_response_with_origin(
+            "INVALID_EXPIRY",
+            &format!("expiresInDays must be between 1 and {}", MAX_SHARE_DAYS),
+            400,
+            &origin,
+        );
+    }
+    let issued_at = Utc::now();
+    let expires_at = issued_at + Duration::days(days);
+
+    let scope = match resolve_scope(&body, expires_at) {
+        Ok(scope) => scope,
+        Err((code, message)) => return error_response_with_origin(code, &message, 400, &origin),
+    };
+
+    let secret = resolve_session_signing_secret(&ctx)
+        .ok_or_else(|| Error::RustError("Session signing secret is not configured".to_string()))?;
+    let claims = ShareClaims {
+        typ: SHARE_TOKEN_TYPE.to_string(),
+        sid: uuid::Uuid::new_v4().to_string(),
+        iat: issued_at.timestamp(),
+        exp: expires_at.timestamp(),
+        scope: scope.clone(),
+    };
+    let token = sign_claims(&claims, &secret)?;
+    console_log!(
+        "Share link {} created for {:?} until {}",
+        claims.sid,
+        scope,
+        expires_at.to_rfc3339()
+    );
+
+    let response = ShareResponse {
+        path: format!("/api/share/{}", token),
+        token,
+        scope,
+        issued_at: issued_at.to_rfc3339(),
+        expires_at: expires_at.to_rfc3339(),
+    };
+
+    let json = serde_json::to_string(&response)?;
+    let headers = build_cors_headers(&origin)?;
+    headers.set("Content-Type", "application/json")?;
+    headers.set("Cache-Control", "no-store, must-revalidate")?;
+
+    Ok(Response::ok(json)?.with_headers(headers))
+}
+
+/// API endpoint: the live data a share link grants, while it is valid
+pub async fn handle_shared(
+    req: Request,
+    ctx: RouteContext<Context>,
+    token: String,
+) -> Result<Response> {
+    let claims = match resolve_session_signing_secret(&ctx)
+        .and_then(|secret| verify_share_token(token.trim(), &secret))
+    {
+        Some(claims) => claims,
+        None => {
+            return error_response(
+                "INVALID_SHARE_LINK",
+                "This share link is invalid or has expired",
+                401,
+            )
+        }
+    };
+
+    let config = match get_config(&ctx.env) {
+        Ok(cfg) => cfg,
+        Err(e) => return error_response("CONFIG_ERROR", &e.to_string(), 500),
+    };
+    let url = req.url()?;
+    let if_none_match = req.headers().get("If-None-Match")?;
+
+    let (json, cache_status) = match &claims.scope {
+        ShareScope::Month { ym } => {
+            let detail = match ShiftDetail::from_query(query_param(&url, "detail").as_deref()) {
+                Some(detail) => detail,
+                None => {
+                    return error_response(
+                        "INVALID_DETAIL",
+                        "Invalid detail value. Expected codes or times",
+                        400,
+                    )
+                }
+            };
+            let (mut month_shifts, cache_status) =
+                match load_month_shifts(&ctx, &config, ym, ScheduleVersion::Live).await {
+                    Ok(result) => result,
+                    Err(failure) => return failure.into_response(),
+                };
+            // Months cached before `days` existed fall back to the built-in calendar
+            if month_shifts.days.is_empty() {
+                month_shifts.days = holidays::HolidayCalendar::default().month_days(ym);
+            }
+            (
+                serde_json::to_string(&month_shifts.with_detail(detail))?,
+                cache_status,
+            )
+        }
+        ShareScope::Person { id, from, to } => {
+            let (Some(start), Some(end)) = (parse_date(from), parse_date(to)) else {
+                return error_response("INVALID_SHARE_LINK", "This share link is invalid", 401);
+            };
+            let (person, shifts, cache_status) =
+                match load_person_shifts(&ctx, &config, id, start, end).await {
+                    Ok(Some(result)) => result,
+                    Ok(None) => {
+                        return error_response(
+                            "PERSON_NOT_FOUND",
+                            "No shifts found for this person in the shared window",
+                            404,
+                        )
+                    }
+                    Err(failure) => return failure.into_response(),
+                };
+            let response = PersonShiftsResponse {
+                person,
+                from: from.clone(),
+                to: to.clone(),
+                shifts,
+            };
+            (serde_json::to_string(&response)?, cache_status)
+        }
+    };
+
+    let mut response = build_success_response(
+        json,
+        response_ttl(&config, cache_status),
+        cache_status,
+        if_none_match.as_deref(),
+    )?;
+    // The token is the credential: keep it out of shared caches and referrers
+    let expires_at = DateTime::from_timestamp(claims.exp, 0)
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_default();
+    let headers = response.headers_mut();
+    headers.set("Cache-Control", "private, no-cache")?;
+    headers.set("Referrer-Policy", "no-referrer")?;
+    headers.set("X-Share-Expires-At", &expires_at)?;
+    headers.set(
+        "Access-Control-Expose-Headers",
+        "ETag, X-Cache-Status, X-Share-Expires-At",
+    )?;
+    Ok(response)
+}
+
+fn verify_share_token(token: &str, secret: &str) -> Option<ShareClaims> {
+    let claims: ShareClaims = verify_claims(token, secret)?;
+    if claims.typ != SHARE_TOKEN_TYPE || claims.exp <= Utc::now().timestamp() {
+        return None;
+    }
+    Some(claims)
+}
+
+/// Validate the requested scope. A person window defaults to the current month
+/// through the month the link expires in.
+fn resolve_scope(
+    body: &ShareRequest,
+    expires_at: DateTime<Utc>,
+) -> std::result::Result<ShareScope, (&'static str, String)> {
+    let trimmed = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+
+    match (trimmed(&body.ym), trimmed(&body.person)) {
+        (Some(ym), None) => {
+            if !is_valid_ym(&ym) {
+                return Err((
+                    "INVALID_YM",
+                    "Invalid ym format. Expected YYYY-MM".to_string(),
+                ));
+            }
+            Ok(ShareScope::Month { ym })
+        }
+        (None, Some(id)) => {
+            let (month_start, _) = current_month_bounds();
+            let start = match trimmed(&body.from) {
+                Some(from) => parse_date(&from).ok_or_else(|| {
+                    (
+                        "INVALID_RANGE",
+                        format!("Invalid date {}. Expected YYYY-MM-DD", from),
+                    )
+                })?,
+                None => month_start,
+            };
+            let end = match trimmed(&body.to) {
+                Some(to) => parse_date(&to).ok_or_else(|| {
+                    (
+                        "INVALID_RANGE",
+                        format!("Invalid date {}. Expected YYYY-MM-DD", to),
+                    )
+                })?,
+                None => {
+                    let (_, expiry_month_end) =
+                        month_dates(&expires_at.format("%Y-%m").to_string())
+                            .ok_or_else(|| ("INVALID_RANGE", "Invalid expiry month".to_string()))?;
+                    expiry_month_end.min(start + Duration::days(MAX_RANGE_DAYS - 1))
+                }
+            };
+
+            if end < start {
+                return Err((
+                    "INVALID_RANGE",
+                    "Range end must not be before its start".to_string(),
+                ));
+            }
+            let span_days = end.signed_duration_since(start).num_days() + 1;
+            if span_days > MAX_RANGE_DAYS {
+                return Err((
+                    "INVALID_RANGE",
+                    format!(
+                        "Range cannot exceed {} days (requested {})",
+                        MAX_RANGE_DAYS, span_days
+                    ),
+                ));
+            }
+
+            Ok(ShareScope::Person {
+                id,
+                from: start.format("%Y-%m-%d").to_string(),
+                to: end.format("%Y-%m-%d").to_string(),
+            })
+        }
+        _ => Err((
+            "INVALID_SCOPE",
+            "Pass exactly one of ym (YYYY-MM) or person (person ID)".to_string(),
+        )),
+    }
+}
+
+/// YYYY-MM-DD within the months the Worker serves
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    let date = NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").ok()?;
+    is_valid_ym(&date.format("%Y-%m").to_string()).then_some(date)
+}
@@ -0,0 +1,434 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use futures::future::join_all;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use worker::*;
+
+use crate::{error_response, is_valid_ym, resolve_bool_var, utils, MonthShifts};
+
+const SNAPSHOT_BUCKET: &str = "SNAPSHOT_BUCKET";
+// Millisecond timestamp, then a content-hash suffix so two archives in the same
+// instant can't overwrite each other: `20250101T120000.123Z-1a2b3c4d`
+const SNAPSHOT_ID_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+// Ids written before the suffix was added
+const LEGACY_SNAPSHOT_ID_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const SNAPSHOT_ID_HASH_CHARS: usize = 8;
+const SNAPSHOT_HASH_METADATA: &str = "sha256";
+// Last version seen by the change poller, kept outside the `<ym>/` snapshot prefixes
+const POLL_STATE_PREFIX: &str = "poll-state";
+
+#[derive(Serialize)]
+struct SnapshotSummary {
+    id: String,
+    key: String,
+    #[serde(rename = "capturedAt")]
+    captured_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+    size: u64,
+}
+
+#[derive(Serialize)]
+struct SnapshotListResponse {
+    ym: String,
+    snapshots: Vec<SnapshotSummary>,
+}
+
+pub struct SnapshotHistory {
+    /// Capture time of the oldest snapshot kept for the month
+    pub first_captured_at: Option<DateTime<Utc>>,
+    pub versions: Vec<(DateTime<Utc>, MonthShifts)>,
+}
+
+fn content_hash(json: &str) -> String {
+    utils::hex_encode(&Sha256::digest(json.as_bytes()))
+}
+
+fn snapshot_key(ym: &str, id: &str) -> String {
+    format!("{}/{}.json", ym, id)
+}
+
+fn snapshot_id(captured_at: DateTime<Utc>, hash: &str) -> String {
+    format!(
+        "{}-{}",
+        captured_at.format(SNAPSHOT_ID_FORMAT),
+        &hash[..SNAPSHOT_ID_HASH_CHARS.min(hash.len())]
+    )
+}
+
+/// Capture time encoded in a snapshot id, in either the current or the legacy format
+fn parse_snapshot_id(id: &str) -> Option<DateTime<Utc>> {
+    let parsed = match id.rsplit_once('-') {
+        Some((timestamp, suffix))
+            if suffix.len() == SNAPSHOT_ID_HASH_CHARS
+                && suffix.bytes().all(|b| b.is_ascii_hexdigit()) =>
+        {
+            NaiveDateTime::parse_from_str(timestamp, SNAPSHOT_ID_FORMAT)
+        }
+        _ => NaiveDateTime::parse_from_str(id, LEGACY_SNAPSHOT_ID_FORMAT),
+    };
+    parsed.ok().map(|captured_at| captured_at.and_utc())
+}
+
+/// Capture time of a listed object under `<ym>/`
+fn snapshot_captured_at(ym: &str, key: &str) -> Option<(String, DateTime<Utc>)> {
+    let id = key
+        .strip_prefix(&format!("{}/", ym))?
+        .strip_suffix(".json")?;
+    Some((id.to_string(), parse_snapshot_id(id)?))
+}
+
+/// All snapshot objects for a month, oldest first
+async fn list_snapshot_objects(bucket: &Bucket, ym: &str) -> Result<Vec<Object>> {
+    let mut objects = Vec::new();
+    let mut cursor: Option<String> = None;
+
+    loop {
+        let mut request = bucket
+            .list()
+            .prefix(format!("{}/", ym))
+            .include(vec![Include::CustomMetadata]);
+        if let Some(cursor) = cursor.take() {
+            request = request.cursor(cursor);
+        }
+
+        let page = request.execute().await?;
+        objects.extend(page.objects());
+
+        match page.cursor() {
+            Some(next) if page.truncated() => cursor = Some(next),
+            _ => break,
+        }
+    }
+
+    // By capture time, so legacy ids and same-second ids still sort chronologically
+    objects.sort_by_cached_key(|object| {
+        let key = object.key();
+        (
+            snapshot_captured_at(ym, &key).map(|(_, captured_at)| captured_at),
+            key,
+        )
+    });
+    Ok(objects)
+}
+
+/// Hash stored on the newest snapshot in R2. Always listed rather than cached per
+/// isolate, since another isolate may have archived a newer version meanwhile.
+async fn latest_snapshot_hash(bucket: &Bucket, ym: &str) -> Result<Option<String>> {
+    let objects = list_snapshot_objects(bucket, ym).await?;
+    Ok(objects.last().and_then(|object| {
+        object
+            .custom_metadata()
+            .ok()
+            .and_then(|metadata| metadata.get(SNAPSHOT_HASH_METADATA).cloned())
+    }))
+}
+
+/// Newest archived version of a month, used as the baseline for change polling
+pub async fn load_latest_snapshot(env: &Env, ym: &str) -> Result<Option<MonthShifts>> {
+    let bucket = match env.bucket(SNAPSHOT_BUCKET) {
+        Ok(bucket) => bucket,
+        Err(_) => return Ok(None),
+    };
+
+    let Some(latest) = list_snapshot_objects(&bucket, ym).await?.pop() else {
+        return Ok(None);
+    };
+
+    read_month_shifts(&bucket, &latest.key()).await
+}
+
+/// Archived versions of a month, oldest first: the newest one captured at or before
+/// `since` (the baseline), then every later one, capped at `limit + 1` versions.
+/// `None` when snapshot storage is not configured.
+pub async fn load_snapshot_history(
+    env: &Env,
+    ym: &str,
+    since: Option<DateTime<Utc>>,
+    limit: usize,
+) -> Result<Option<SnapshotHistory>> {
+    let bucket = match env.bucket(SNAPSHOT_BUCKET) {
+        Ok(bucket) => bucket,
+        Err(_) => return Ok(None),
+    };
+
+    let captured: Vec<(DateTime<Utc>, String)> = list_snapshot_objects(&bucket, ym)
+        .await?
+        .iter()
+        .filter_map(|object| {
+            let key = object.key();
+            let (_, captured_at) = snapshot_captured_at(ym, &key)?;
+            Some((captured_at, key))
+        })
+        .collect();
+
+    let first_after = since.map_or(0, |since| {
+        captured.partition_point(|(captured_at, _)| *captured_at <= since)
+    });
+    let start = first_after
+        .saturating_sub(1)
+        .max(captured.len().saturating_sub(limit + 1));
+
+    let reads = join_all(
+        captured[start..]
+            .iter()
+            .map(|(_, key)| read_month_shifts(&bucket, key)),
+    )
+    .await;
+    let mut versions = Vec::new();
+    for ((captured_at, _), month_shifts) in captured[start..].iter().zip(reads) {
+        if let Some(month_shifts) = month_shifts? {
+            versions.push((*captured_at, month_shifts));
+        }
+    }
+
+    Ok(Some(SnapshotHistory {
+        first_captured_at: captured.first().map(|(captured_at, _)| *captured_at),
+        versions,
+    }))
+}
+
+async fn read_month_shifts(bucket: &Bucket, key: &str) -> Result<Option<MonthShifts>> {
+    let object = match bucket.get(key).execute().await? {
+        Some(object) => object,
+        None => return Ok(None),
+    };
+    let body = match object.body() {
+        Some(body) => body.text().await?,
+        None => return Ok(None),
+    };
+
+    serde_json::from_str(&body)
+        .map(Some)
+        .map_err(|e| Error::RustError(format!("Invalid schedule JSON in {}: {}", key, e)))
+}
+
+/// Version the change poller compared against on its previous run
+pub async fn load_poll_state(env: &Env, ym: &str) -> Result<Option<MonthShifts>> {
+    match env.bucket(SNAPSHOT_BUCKET) {
+        Ok(bucket) => {
+            read_month_shifts(&bucket, &format!("{}/{}.json", POLL_STATE_PREFIX, ym)).await
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+pub async fn store_poll_state(env: &Env, month_shifts: &MonthShifts) -> Result<()> {
+    let bucket = match env.bucket(SNAPSHOT_BUCKET) {
+        Ok(bucket) => bucket,
+        Err(_) => return Ok(()),
+    };
+
+    let json = serde_json::to_vec(month_shifts)
+        .map_err(|e| Error::RustError(format!("Failed to serialize poll state: {}", e)))?;
+    bucket
+        .put(
+            format!("{}/{}.json", POLL_STATE_PREFIX, month_shifts.ym),
+            json,
+        )
+        .execute()
+        .await
+        .map_err(|e| Error::RustError(format!("Failed to write poll state to R2: {:?}", e)))?;
+
+    Ok(())
+}
+
+/// Store a freshly transformed month in R2 unless it matches the newest snapshot
+pub async fn archive_schedule_snapshot(env: &Env, month_shifts: &MonthShifts) -> Result<()> {
+    if !resolve_bool_var(env, "SCHEDULE_SNAPSHOTS_TO_R2", true) {
+        return Ok(());
+    }
+
+    let bucket = match env.bucket(SNAPSHOT_BUCKET) {
+        Ok(bucket) => bucket,
+        Err(_) => {
+            console_log!("SNAPSHOT_BUCKET not configured, skipping schedule snapshot");
+            return Ok(());
+        }
+    };
+
+    let json = serde_json::to_string(month_shifts)
+        .map_err(|e| Error::RustError(format!("Failed to serialize snapshot: {}", e)))?;
+    let hash = content_hash(&json);
+
+    if latest_snapshot_hash(&bucket, &month_shifts.ym)
+        .await?
+        .as_deref()
+        == Some(hash.as_str())
+    {
+        console_log!(
+            "Schedule snapshot for {} unchanged ({}), skipping",
+            month_shifts.ym,
+            hash
+        );
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    let key = snapshot_key(&month_shifts.ym, &snapshot_id(now, &hash));
+
+    let mut metadata = HashMap::new();
+    metadata.insert(SNAPSHOT_HASH_METADATA.to_string(), hash);
+    metadata.insert("capturedAt".to_string(), now.to_rfc3339());
+
+    bucket
+        .put(&key, json.into_bytes())
+        .http_metadata(HttpMetadata {
+            content_type: Some("application/json".to_string()),
+            ..Default::default()
+        })
+        .custom_metadata(metadata)
+        .execute()
+        .await
+        .map_err(|e| Error::RustError(format!("Failed to write snapshot to R2: {:?}", e)))?;
+
+    console_log!("Schedule snapshot written to R2: {}", key);
+    Ok(())
+}
+
+fn is_valid_snapshot_id(id: &str) -> bool {
+    parse_snapshot_id(id).is_some()
+}
+
+/// API endpoint: list stored snapshots for a month
+pub async fn handle_list_snapshots(ctx: RouteContext<Context>, ym: String) -> Result<Response> {
+    if !is_valid_ym(&ym) {
+        return error_response("INVALID_YM", "Invalid ym format. Expected YYYY-MM", 400);
+    }
+
+    let bucket = match ctx.bucket(SNAPSHOT_BUCKET) {
+        Ok(bucket) => bucket,
+        Err(_) => return error_response("CONFIG_ERROR", "Snapshot storage not configured", 500),
+    };
+
+    let objects = match list_snapshot_objects(&bucket, &ym).await {
+        Ok(objects) => objects,
+        Err(e) => {
+            console_error!("Failed to list snapshots for {}: {:?}", ym, e);
+            return error_response("STORAGE_ERROR", "Failed to list snapshots", 500);
+        }
+    };
+
+    let snapshots = objects
+        .iter()
+        .filter_map(|object| {
+            let key = object.key();
+            let (id, captured_at) = snapshot_captured_at(&ym, &key)?;
+            let captured_at = captured_at.to_rfc3339();
+            let sha256 = object
+                .custom_metadata()
+                .ok()
+                .and_then(|metadata| metadata.get(SNAPSHOT_HASH_METADATA).cloned());
+
+            Some(SnapshotSummary {
+                id,
+                key,
+                captured_at,
+                sha256,
+                size: object.size(),
+            })
+        })
+        .collect();
+
+    let json = serde_json::to_string(&SnapshotListResponse { ym, snapshots })?;
+    let headers = Headers::new();
+    headers.set("Content-Type", "application/json")?;
+    headers.set("Cache-Control", "no-store")?;
+    headers.set("Access-Control-Allow-Origin", "*")?;
+
+    Ok(Response::ok(json)?.with_headers(headers))
+}
+
+/// API endpoint: fetch one historic snapshot
+pub async fn handle_get_snapshot(
+    ctx: RouteContext<Context>,
+    ym: String,
+    id: String,
+) -> Result<Response> {
+    if !is_valid_ym(&ym) {
+        return error_response("INVALID_YM", "Invalid ym format. Expected YYYY-MM", 400);
+    }
+
+    let id = id.trim_end_matches(".json").to_string();
+    if !is_valid_snapshot_id(&id) {
+        return error_response(
+            "INVALID_SNAPSHOT",
+            "Invalid snapshot id. Expected an id from the snapshot list",
+            400,
+        );
+    }
+
+    let bucket = match ctx.bucket(SNAPSHOT_BUCKET) {
+        Ok(bucket) => bucket,
+        Err(_) => return error_response("CONFIG_ERROR", "Snapshot storage not configured", 500),
+    };
+
+    let key = snapshot_key(&ym, &id);
+    let object = match bucket.get(&key).execute().await? {
+        Some(object) => object,
+        None => return error_response("NOT_FOUND", "Snapshot not found", 404),
+    };
+
+    let sha256 = object
+        .custom_metadata()
+        .ok()
+        .and_then(|metadata| metadata.get(SNAPSHOT_HASH_METADATA).cloned());
+    let body = match object.body() {
+        Some(body) => body.text().await?,
+        None => return error_response("STORAGE_ERROR", "Snapshot has no body", 500),
+    };
+
+    let headers = Headers::new();
+    headers.set("Content-Type", "application/json")?;
+    // Snapshots never change once written
+    headers.set("Cache-Control", "public, max-age=31536000, immutable")?;
+    headers.set("Access-Control-Allow-Origin", "*")?;
+    if let Some(sha256) = sha256 {
+        headers.set("X-Snapshot-Sha256", &sha256)?;
+    }
+
+    Ok(Response::ok(body)?.with_headers(headers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn snapshot_ids_keep_milliseconds_and_a_hash_suffix() {
+        let captured_at = Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap()
+            + chrono::Duration::milliseconds(678);
+        let id = snapshot_id(captured_at, "1a2b3c4d5e6f");
+
+        assert_eq!(id, "20250102T030405.678Z-1a2b3c4d");
+        assert_eq!(parse_snapshot_id(&id), Some(captured_at));
+        assert_ne!(id, snapshot_id(captured_at, "ffffffff0000"));
+    }
+
+    #[test]
+    fn legacy_snapshot_ids_still_parse() {
+        assert_eq!(
+            parse_snapshot_id("20250102T030405Z"),
+            Some(Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap())
+        );
+        assert_eq!(
+            snapshot_captured_at("2025-01", "2025-01/20250102T030405Z.json").map(|(id, _)| id),
+            Some("20250102T030405Z".to_string())
+        );
+    }
+
+    #[test]
+    fn malformed_snapshot_ids_are_rejected() {
+        for id in [
+            "",
+            "latest",
+            "20250102T030405.678Z-xyz",
+            "20250102T030405.678Z-zzzzzzzz",
+            "../2025-02/20250102T030405Z",
+        ] {
+            assert!(!is_valid_snapshot_id(id), "{}", id);
+        }
+    }
+}
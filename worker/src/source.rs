@@ -0,0 +1,502 @@
+use chrono::{DateTime, Utc};
+use futures::future::{select, Either};
+use std::time::Duration;
+use worker::*;
+
+use crate::{Config, ScheduleVersion, UpstreamFailure, UpstreamResponse, UpstreamShift};
+
+const UPSTREAM_TIMEOUT_MESSAGE: &str = "Upstream request timed out";
+const DEFAULT_FIXTURE_KEY: &str = "fixtures/schedule.json";
+const RETRY_BASE_DELAY_MS: u64 = 200;
+const RETRY_MAX_DELAY_MS: u64 = 2000;
+
+/// Where month schedules come from, selected by `SCHEDULE_SOURCE`
+#[derive(Clone, Copy, PartialEq)]
+pub enum SourceKind {
+    MetricAid,
+    Fixture,
+}
+
+impl SourceKind {
+    pub fn from_env(env: &Env) -> Self {
+        match env.var("SCHEDULE_SOURCE").map(|v| v.to_string()) {
+            Ok(value) if value.trim().eq_ignore_ascii_case("fixture") => SourceKind::Fixture,
+            _ => SourceKind::MetricAid,
+        }
+    }
+}
+
+/// A provider of raw upstream shifts
+pub trait ScheduleSource {
+    /// Shifts of one roster version starting between two inclusive dates (YYYY-MM-DD)
+    async fn fetch_shifts(
+        &self,
+        start_date: &str,
+        end_date: &str,
+        version: ScheduleVersion,
+    ) -> std::result::Result<Vec<UpstreamShift>, UpstreamFailure>;
+}
+
+/// The live MetricAid `public/schedule` API
+pub struct MetricAidSource {
+    base_url: String,
+    token: String,
+    timeout_ms: u64,
+    budget_ms: u64,
+}
+
+impl MetricAidSource {
+    pub fn from_config(config: &Config) -> Self {
+        MetricAidSource {
+            base_url: config.api_base_url.clone(),
+            token: config.api_token.clone(),
+            timeout_ms: config.api_timeout_ms,
+            budget_ms: config.api_budget_ms,
+        }
+    }
+}
+
+impl ScheduleSource for MetricAidSource {
+    async fn fetch_shifts(
+        &self,
+        start_date: &str,
+        end_date: &str,
+        version: ScheduleVersion,
+    ) -> std::result::Result<Vec<UpstreamShift>, UpstreamFailure> {
+        // Build upstream URL with token as query parameter
+        let upstream_url = format!(
+            "{}/public/schedule?token={}&startDate={}&endDate={}&scheduleVersion={}",
+            self.base_url,
+            self.token,
+            start_date,
+            end_date,
+            version.as_str()
+        );
+
+        let upstream_data =
+            match fetch_with_retry(&upstream_url, self.timeout_ms, self.budget_ms, 2, None).await {
+                Ok(data) => data,
+                Err(e) => {
+                    let message = e.to_string();
+                    if message == UPSTREAM_TIMEOUT_MESSAGE {
+                        return Err(UpstreamFailure::Timeout(message));
+                    }
+                    return Err(UpstreamFailure::Unavailable(message));
+                }
+            };
+
+        parse_upstream_payload(&upstream_data)
+    }
+}
+
+/// A fixed set of shifts in the upstream payload format, for tests and offline dev
+pub struct FixtureSource {
+    shifts: Vec<UpstreamShift>,
+}
+
+impl FixtureSource {
+    pub fn new(shifts: Vec<UpstreamShift>) -> Self {
+        FixtureSource { shifts }
+    }
+
+    pub fn from_json(json: &str) -> std::result::Result<Self, UpstreamFailure> {
+        parse_upstream_payload(json).map(FixtureSource::new)
+    }
+
+    /// Load the fixture named by `SCHEDULE_FIXTURE_KEY` from `CONFIG_BUCKET`
+    pub async fn from_r2(env: &Env) -> std::result::Result<Self, UpstreamFailure> {
+        let key = env
+            .var("SCHEDULE_FIXTURE_KEY")
+            .map(|v| v.to_string())
+            .unwrap_or_else(|_| DEFAULT_FIXTURE_KEY.to_string());
+        let unavailable = |e: Error| {
+            UpstreamFailure::Unavailable(format!("Failed to load fixture {}: {}", key, e))
+        };
+
+        let bucket = env.bucket("CONFIG_BUCKET").map_err(unavailable)?;
+        let object = bucket
+            .get(&key)
+            .execute()
+            .await
+            .map_err(unavailable)?
+            .ok_or_else(|| UpstreamFailure::Unavailable(format!("Fixture {} not found", key)))?;
+        let body = object
+            .body()
+            .ok_or_else(|| UpstreamFailure::Unavailable(format!("Fixture {} has no body", key)))?;
+        let json = body.text().await.map_err(unavailable)?;
+
+        FixtureSource::from_json(&json)
+    }
+}
+
+impl ScheduleSource for FixtureSource {
+    async fn fetch_shifts(
+        &self,
+        start_date: &str,
+        end_date: &str,
+        _version: ScheduleVersion,
+    ) -> std::result::Result<Vec<UpstreamShift>, UpstreamFailure> {
+        // A fixture holds a single roster, served for every version.
+        // Timestamps start with YYYY-MM-DD, so a string comparison on the date is enough
+        Ok(self
+            .shifts
+            .iter()
+            .filter(|shift| {
+                let date = shift.start_time.get(..10).unwrap_or("");
+                date >= start_date && date <= end_date
+            })
+            .cloned()
+            .collect())
+    }
+}
+
+/// The source configured for this deployment
+pub enum ConfiguredSource {
+    MetricAid(MetricAidSource),
+    Fixture(FixtureSource),
+}
+
+impl ConfiguredSource {
+    pub async fn from_env(
+        env: &Env,
+        config: &Config,
+    ) -> std::result::Result<Self, UpstreamFailure> {
+        match config.schedule_source {
+            SourceKind::MetricAid => Ok(ConfiguredSource::MetricAid(MetricAidSource::from_config(
+                config,
+            ))),
+            SourceKind::Fixture => FixtureSource::from_r2(env)
+                .await
+                .map(ConfiguredSource::Fixture),
+        }
+    }
+}
+
+impl ScheduleSource for ConfiguredSource {
+    async fn fetch_shifts(
+        &self,
+        start_date: &str,
+        end_date: &str,
+        version: ScheduleVersion,
+    ) -> std::result::Result<Vec<UpstreamShift>, UpstreamFailure> {
+        match self {
+            ConfiguredSource::MetricAid(source) => {
+                source.fetch_shifts(start_date, end_date, version).await
+            }
+            ConfiguredSource::Fixture(source) => {
+                source.fetch_shifts(start_date, end_date, version).await
+            }
+        }
+    }
+}
+
+fn parse_upstream_payload(json: &str) -> std::result::Result<Vec<UpstreamShift>, UpstreamFailure> {
+    serde_json::from_str::<UpstreamResponse>(json)
+        .map(|upstream| upstream.data)
+        .map_err(|e| UpstreamFailure::InvalidPayload(e.to_string()))
+}
+
+/// Result of a single upstream attempt that completed before its deadline
+enum FetchAttempt {
+    Success(String),
+    Status {
+        code: u16,
+        retry_after_ms: Option<u64>,
+    },
+}
+
+/// GET with a per-attempt deadline, jittered exponential backoff and an overall budget.
+/// 5xx/429 responses and network failures are retried; a timeout yields
+/// `UPSTREAM_TIMEOUT_MESSAGE` so callers can map it to 504.
+async fn fetch_with_retry(
+    url: &str,
+    timeout_ms: u64,
+    budget_ms: u64,
+    max_retries: u32,
+    headers: Option<&[(String, String)]>,
+) -> Result<String> {
+    let started = Utc::now();
+    let elapsed_ms = || {
+        Utc::now()
+            .signed_duration_since(started)
+            .num_milliseconds()
+            .max(0) as u64
+    };
+    let mut retries = 0;
+
+    loop {
+        let remaining_ms = budget_ms.saturating_sub(elapsed_ms());
+        if remaining_ms == 0 {
+            return Err(Error::RustError(UPSTREAM_TIMEOUT_MESSAGE.to_string()));
+        }
+
+        let mut request_init = RequestInit::new();
+        request_init.with_method(Method::Get);
+
+        let mut request = Request::new_with_init(url, &request_init)?;
+
+        if let Some(headers) = headers {
+            for (name, value) in headers {
+                request.headers_mut()?.set(name, value)?;
+            }
+        }
+
+        let controller = AbortController::default();
+        let signal = controller.signal();
+        let attempt = Box::pin(async move {
+            let mut response = Fetch::Request(request).send_with_signal(&signal).await?;
+            let code = response.status_code();
+            if (200..300).contains(&code) {
+                return Ok(FetchAttempt::Success(response.text().await?));
+            }
+            let retry_after_ms = response
+                .headers()
+                .get("Retry-After")
+                .ok()
+                .flatten()
+                .and_then(|value| parse_retry_after_ms(&value));
+            Ok::<_, Error>(FetchAttempt::Status {
+                code,
+                retry_after_ms,
+            })
+        });
+        let deadline = Box::pin(Delay::from(Duration::from_millis(
+            timeout_ms.min(remaining_ms),
+        )));
+
+        let (error, retry_after_ms) = match select(attempt, deadline).await {
+            Either::Left((Ok(FetchAttempt::Success(body)), _)) => return Ok(body),
+            Either::Left((
+                Ok(FetchAttempt::Status {
+                    code,
+                    retry_after_ms,
+                }),
+                _,
+            )) => {
+                let error = Error::RustError(format!("Upstream returned status {}", code));
+                if code < 500 && code != 429 {
+                    return Err(error);
+                }
+                (error, retry_after_ms)
+            }
+            Either::Left((Err(e), _)) => (e, None),
+            Either::Right(_) => {
+                controller.abort();
+                (Error::RustError(UPSTREAM_TIMEOUT_MESSAGE.to_string()), None)
+            }
+        };
+
+        if retries >= max_retries {
+            return Err(error);
+        }
+
+        // Honour Retry-After when given, otherwise back off exponentially with full jitter
+        let delay_ms = retry_after_ms.unwrap_or_else(|| backoff_delay_ms(retries));
+        if elapsed_ms() + delay_ms >= budget_ms {
+            console_log!(
+                "Giving up on upstream after {} attempt(s): retry would exceed the {}ms budget",
+                retries + 1,
+                budget_ms
+            );
+            return Err(error);
+        }
+
+        retries += 1;
+        Delay::from(Duration::from_millis(delay_ms)).await;
+    }
+}
+
+/// Full-jitter backoff: uniform in [0, min(cap, base * 2^attempt)]
+fn backoff_delay_ms(attempt: u32) -> u64 {
+    let ceiling = RETRY_BASE_DELAY_MS
+        .saturating_mul(1u64 << attempt.min(16))
+        .min(RETRY_MAX_DELAY_MS);
+    // v4 UUIDs come from the platform CSPRNG, which is all we need for jitter
+    let random = uuid::Uuid::new_v4().as_u128() as u64;
+    random % (ceiling + 1)
+}
+
+/// Parse `Retry-After` as delay-seconds or an HTTP-date
+fn parse_retry_after_ms(value: &str) -> Option<u64> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(seconds.saturating_mul(1000));
+    }
+
+    DateTime::parse_from_rfc2822(value).ok().map(|at| {
+        at.with_timezone(&Utc)
+            .signed_duration_since(Utc::now())
+            .num_milliseconds()
+            .max(0) as u64
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config, holidays, names, transform_to_month_shifts, MonthShifts, ShiftCell, ShiftDetail,
+    };
+    use futures::executor::block_on;
+
+    const FIXTURE: &str = include_str!("../fixtures/schedule.json");
+
+    /// Fetch a month's window (plus the day before) from a fixture and transform it
+    fn month_from_fixture(json: &str, ym: &str, fetch_start: &str, end: &str) -> MonthShifts {
+        let source = FixtureSource::from_json(json)
+            .unwrap_or_else(|failure| panic!("invalid fixture: {}", failure.message()));
+        let shifts = block_on(source.fetch_shifts(fetch_start, end, ScheduleVersion::Live))
+            .unwrap_or_else(|failure| panic!("fixture fetch failed: {}", failure.message()));
+
+        transform_to_month_shifts(
+            ym.to_string(),
+            shifts,
+            &config::ShiftDisplayConfig::default(),
+            &names::NameConfig::default(),
+            &config::GroupsConfig::default(),
+            &holidays::HolidayCalendar::default(),
+        )
+    }
+
+    fn row<'a>(month: &'a MonthShifts, person_id: &str) -> &'a [Option<Vec<ShiftCell>>] {
+        let idx = month
+            .people
+            .iter()
+            .position(|person| person.id == person_id)
+            .unwrap_or_else(|| panic!("person {} missing", person_id));
+        &month.rows[idx]
+    }
+
+    fn codes(cell: &Option<Vec<ShiftCell>>) -> Vec<&str> {
+        cell.iter().flatten().map(ShiftCell::code).collect()
+    }
+
+    #[test]
+    fn fixture_month_fills_rows_by_start_day() {
+        let month = month_from_fixture(FIXTURE, "2025-01", "2024-12-31", "2025-01-31");
+
+        assert_eq!(month.people.len(), 3);
+        assert_eq!(month.codes, ["M", "N", "P"]);
+        assert!(month.rows.iter().all(|row| row.len() == 31));
+        assert!(month.carry_in.is_empty());
+
+        let giulia = row(&month, "101");
+        assert_eq!(codes(&giulia[1]), ["M"]);
+        assert_eq!(codes(&giulia[2]), ["P"]);
+        assert!(giulia[0].is_none());
+
+        let marco = row(&month, "102");
+        assert_eq!(codes(&marco[1]), ["P"]);
+        assert_eq!(codes(&marco[2]), ["M"]);
+
+        // The night shift stays on the day it starts
+        let sara = row(&month, "103");
+        assert_eq!(codes(&sara[1]), ["N"]);
+        assert!(sara[2].is_none());
+    }
+
+    #[test]
+    fn fixture_month_projects_onto_codes() {
+        let month = month_from_fixture(FIXTURE, "2025-01", "2024-12-31", "2025-01-31");
+        assert!(month
+            .rows
+            .iter()
+            .flatten()
+            .flatten()
+            .flatten()
+            .all(|cell| matches!(cell, ShiftCell::Timed(_))));
+
+        let codes_only = month.clone().with_detail(ShiftDetail::Codes);
+        assert!(codes_only
+            .rows
+            .iter()
+            .flatten()
+            .flatten()
+            .flatten()
+            .all(|cell| matches!(cell, ShiftCell::Code(_))));
+        assert_eq!(codes(&row(&codes_only, "101")[1]), ["M"]);
+
+        let timed = month.with_detail(ShiftDetail::Times);
+        assert!(matches!(
+            row(&timed, "101")[1].as_deref(),
+            Some([ShiftCell::Timed(_)])
+        ));
+    }
+
+    #[test]
+    fn fixture_overnight_from_previous_month_becomes_carry_in() {
+        let json = r##"{
+          "data": [
+            {
+              "start_time": "2025-01-31 20:00:00",
+              "end_time": "2025-02-01 08:00:00",
+              "shift": { "name": "Notte", "alias": "N 8:00PM - 8:00AM" },
+              "user": { "id": 103, "fname": "Sara", "lname": "Conti" }
+            },
+            {
+              "start_time": "2025-01-31 08:00:00",
+              "end_time": "2025-01-31 14:00:00",
+              "shift": { "name": "Mattino", "alias": "M 8:00AM - 2:00PM" },
+              "user": { "id": 104, "fname": "Luca", "lname": "Ferri" }
+            },
+            {
+              "start_time": "2025-02-01 08:00:00",
+              "end_time": "2025-02-01 14:00:00",
+              "shift": { "name": "Mattino", "alias": "M 8:00AM - 2:00PM" },
+              "user": { "id": 101, "fname": "Giulia", "lname": "Bianchi" }
+            },
+            {
+              "start_time": "2025-03-01 08:00:00",
+              "end_time": "2025-03-01 14:00:00",
+              "shift": { "name": "Mattino", "alias": "M 8:00AM - 2:00PM" },
+              "user": { "id": 105, "fname": "Anna", "lname": "Greco" }
+            }
+          ]
+        }"##;
+        let month = month_from_fixture(json, "2025-02", "2025-01-31", "2025-02-28");
+
+        // Only the overnight shift survives from the extra day; March is outside the window
+        let mut ids: Vec<&str> = month.people.iter().map(|p| p.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, ["101", "103"]);
+        assert!(month.rows.iter().all(|row| row.len() == 28));
+
+        assert_eq!(month.carry_in.len(), month.people.len());
+        for (person, carried) in month.people.iter().zip(&month.carry_in) {
+            match person.id.as_str() {
+                "103" => assert_eq!(codes(carried), ["N"]),
+                _ => assert!(carried.is_none()),
+            }
+        }
+        assert!(row(&month, "103").iter().all(Option::is_none));
+        assert_eq!(codes(&row(&month, "101")[0]), ["M"]);
+    }
+
+    #[test]
+    fn backoff_stays_within_the_capped_window() {
+        for _ in 0..50 {
+            assert!(backoff_delay_ms(0) <= RETRY_BASE_DELAY_MS);
+            assert!(backoff_delay_ms(2) <= 4 * RETRY_BASE_DELAY_MS);
+            assert!(backoff_delay_ms(5) <= RETRY_MAX_DELAY_MS);
+            assert!(backoff_delay_ms(u32::MAX) <= RETRY_MAX_DELAY_MS);
+        }
+    }
+
+    #[test]
+    fn retry_after_seconds_and_http_dates() {
+        assert_eq!(parse_retry_after_ms("120"), Some(120_000));
+        assert_eq!(parse_retry_after_ms(" 0 "), Some(0));
+        assert_eq!(
+            parse_retry_after_ms("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(0)
+        );
+
+        let in_a_minute = (Utc::now() + chrono::Duration::seconds(60)).to_rfc2822();
+        let delay = parse_retry_after_ms(&in_a_minute).expect("HTTP-date");
+        assert!((55_000..=60_000).contains(&delay), "{}", delay);
+
+        for invalid in ["", "soon", "-1", "1.5"] {
+            assert_eq!(parse_retry_after_ms(invalid), None, "{}", invalid);
+        }
+    }
+}
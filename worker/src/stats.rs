@@ -0,0 +1,206 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use worker::*;
+
+use crate::config::{self, StatsConfig};
+use crate::holidays::HolidayCalendar;
+use crate::people::{collect_person_shifts, PersonShift};
+use crate::{
+    build_success_response, error_response, get_config, get_month_bounds, groups, holidays,
+    is_valid_ym, query_param, range, response_ttl, MonthShifts, Person, ScheduleVersion,
+};
+
+#[derive(Serialize, Default)]
+struct CodeStats {
+    count: usize,
+    hours: f64,
+}
+
+#[derive(Serialize)]
+struct PersonStats {
+    person: Person,
+    shifts: usize,
+    #[serde(rename = "totalHours")]
+    total_hours: f64,
+    #[serde(rename = "nightShifts")]
+    night_shifts: usize,
+    #[serde(rename = "weekendShifts")]
+    weekend_shifts: usize,
+    #[serde(rename = "holidayShifts")]
+    holiday_shifts: usize,
+    #[serde(rename = "byCode")]
+    by_code: BTreeMap<String, CodeStats>,
+}
+
+#[derive(Serialize)]
+struct StatsReport {
+    from: String,
+    to: String,
+    #[serde(rename = "nightCodes")]
+    night_codes: Vec<String>,
+    people: Vec<PersonStats>,
+}
+
+/// API endpoint: per-person workload summary for a month (`ym`) or a range (`from`/`to`)
+pub async fn handle_stats(req: Request, ctx: RouteContext<Context>) -> Result<Response> {
+    let config = match get_config(&ctx.env) {
+        Ok(cfg) => cfg,
+        Err(e) => return error_response("CONFIG_ERROR", &e.to_string(), 500),
+    };
+
+    let url = req.url()?;
+    let (start, end) = match range::parse_range_query(&url) {
+        Some(Ok(bounds)) => bounds,
+        Some(Err(message)) => return error_response("INVALID_RANGE", &message, 400),
+        None => {
+            let ym = match query_param(&url, "ym") {
+                Some(v) => v,
+                None => {
+                    return error_response(
+                        "MISSING_PARAM",
+                        "Missing required parameter: ym (or from/to)",
+                        400,
+                    )
+                }
+            };
+            if !is_valid_ym(&ym) {
+                return error_response("INVALID_YM", "Invalid ym format. Expected YYYY-MM", 400);
+            }
+            match month_dates(&ym) {
+                Some(bounds) => bounds,
+                None => {
+                    return error_response("INVALID_YM", "Invalid ym format. Expected YYYY-MM", 400)
+                }
+            }
+        }
+    };
+
+    let stats_config: StatsConfig = config::load_json_config(&ctx.env, config::CONFIG_STATS).await;
+    let calendar = holidays::load_holiday_calendar(&ctx.env).await;
+
+    let months = range::months_in_range(start, end);
+    let results = range::load_months(&ctx, &config, &months, ScheduleVersion::Live).await;
+
+    let mut statuses: HashSet<&'static str> = HashSet::new();
+    let mut loaded: Vec<MonthShifts> = Vec::with_capacity(months.len());
+    for result in results {
+        match result {
+            Ok((month_shifts, status)) => {
+                statuses.insert(status);
+                loaded.push(month_shifts);
+            }
+            Err(failure) => return failure.into_response(),
+        }
+    }
+
+    let mut people: Vec<Person> = Vec::new();
+    let mut shifts_by_person: HashMap<String, Vec<PersonShift>> = HashMap::new();
+    for month_shifts in &loaded {
+        for person in &month_shifts.people {
+            if let Some((found, shifts)) =
+                collect_person_shifts(month_shifts, &person.id, start, end)
+            {
+                if !shifts_by_person.contains_key(&found.id) {
+                    people.push(found.clone());
+                }
+                shifts_by_person.entry(found.id).or_default().extend(shifts);
+            }
+        }
+    }
+
+    // Same order as the grid
+    groups::sort_people(&mut people, &groups::merge_groups(&loaded));
+
+    let report_people: Vec<PersonStats> = people
+        .into_iter()
+        .filter_map(|person| {
+            let shifts = shifts_by_person.remove(&person.id)?;
+            Some(summarize_person(person, &shifts, &stats_config, &calendar))
+        })
+        .filter(|stats| stats.shifts > 0)
+        .collect();
+
+    let report = StatsReport {
+        from: start.format("%Y-%m-%d").to_string(),
+        to: end.format("%Y-%m-%d").to_string(),
+        night_codes: stats_config.night_codes.clone(),
+        people: report_people,
+    };
+
+    let cache_status = if statuses.contains("STALE") {
+        "STALE"
+    } else if statuses.len() == 1 && statuses.contains("HIT") {
+        "HIT"
+    } else {
+        "MISS"
+    };
+
+    let json = serde_json::to_string(&report)?;
+    let if_none_match = req.headers().get("If-None-Match")?;
+    build_success_response(
+        json,
+        response_ttl(&config, cache_status),
+        cache_status,
+        if_none_match.as_deref(),
+    )
+}
+
+pub fn month_dates(ym: &str) -> Option<(NaiveDate, NaiveDate)> {
+    let (start, end) = get_month_bounds(ym).ok()?;
+    Some((
+        NaiveDate::parse_from_str(&start, "%Y-%m-%d").ok()?,
+        NaiveDate::parse_from_str(&end, "%Y-%m-%d").ok()?,
+    ))
+}
+
+/// Weekend and holiday shifts are counted by the day the shift starts
+fn summarize_person(
+    person: Person,
+    shifts: &[PersonShift],
+    stats_config: &StatsConfig,
+    calendar: &HolidayCalendar,
+) -> PersonStats {
+    let mut stats = PersonStats {
+        person,
+        shifts: 0,
+        total_hours: 0.0,
+        night_shifts: 0,
+        weekend_shifts: 0,
+        holiday_shifts: 0,
+        by_code: BTreeMap::new(),
+    };
+
+    for shift in shifts {
+        let hours = shift.duration_minutes.unwrap_or(0) as f64 / 60.0;
+        stats.shifts += 1;
+        stats.total_hours += hours;
+
+        if stats_config.is_night(&shift.code) {
+            stats.night_shifts += 1;
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(&shift.date, "%Y-%m-%d") {
+            if matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+                stats.weekend_shifts += 1;
+            }
+            if calendar.holiday(date).is_some() {
+                stats.holiday_shifts += 1;
+            }
+        }
+
+        let code_stats = stats.by_code.entry(shift.code.clone()).or_default();
+        code_stats.count += 1;
+        code_stats.hours += hours;
+    }
+
+    stats.total_hours = round_hours(stats.total_hours);
+    for code_stats in stats.by_code.values_mut() {
+        code_stats.hours = round_hours(code_stats.hours);
+    }
+
+    stats
+}
+
+pub fn round_hours(hours: f64) -> f64 {
+    (hours * 100.0).round() / 100.0
+}
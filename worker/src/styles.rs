@@ -0,0 +1,299 @@
+use futures::future::join3;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use worker::*;
+
+use crate::config::{
+    self, ColorFallback, RawShiftDisplayConfig, ShiftColorsConfig, ShiftDisplayConfig,
+    ShiftStylingConfig,
+};
+use crate::holidays::DayInfo;
+use crate::ShiftCell;
+
+/// `shift-colors.json`, `shift-styling.config.json` and the display aliases, for `?styled=1`
+#[derive(Default)]
+pub struct StyleConfig {
+    pub colors: ShiftColorsConfig,
+    pub styling: ShiftStylingConfig,
+    pub display: ShiftDisplayConfig,
+}
+
+/// Colours for one code, as `getShiftColor` renders them in the grid
+#[derive(Serialize)]
+pub struct CodeStyle {
+    pub background: String,
+    pub text: String,
+    /// `config` (shift-colors.json), `upstream` (MetricAid) or `generated`
+    pub source: &'static str,
+}
+
+/// A cell entry that needs more than its code's colours: `rows[row][day][entry]`
+#[derive(Serialize)]
+pub struct CellStyle {
+    pub row: usize,
+    pub day: usize,
+    pub entry: usize,
+    pub underline: bool,
+}
+
+#[derive(Serialize)]
+pub struct ShiftStyles {
+    pub codes: BTreeMap<String, CodeStyle>,
+    pub cells: Vec<CellStyle>,
+}
+
+/// A month or range response with its `styles` table alongside
+#[derive(Serialize)]
+pub struct Styled<T: Serialize> {
+    #[serde(flatten)]
+    pub body: T,
+    pub styles: ShiftStyles,
+}
+
+/// Load the style configs from R2, falling back to defaults on failure
+pub async fn load_style_config(env: &Env) -> StyleConfig {
+    let (colors, styling, display) = join3(
+        config::load_json_config(env, config::CONFIG_COLORS),
+        config::load_json_config(env, config::CONFIG_STYLING),
+        config::load_json_config::<RawShiftDisplayConfig>(env, config::CONFIG_DISPLAY),
+    )
+    .await;
+
+    StyleConfig {
+        colors,
+        styling,
+        display: ShiftDisplayConfig::from(display),
+    }
+}
+
+impl StyleConfig {
+    /// Style table for `codes` plus the cells matched by the conditional underline.
+    /// `days` describes each column of `rows`.
+    pub fn resolve(
+        &self,
+        rows: &[Vec<Option<Vec<ShiftCell>>>],
+        days: &[DayInfo],
+        codes: &[String],
+        upstream_colors: &BTreeMap<String, String>,
+    ) -> ShiftStyles {
+        let codes = codes
+            .iter()
+            .map(|code| (code.clone(), self.code_style(code, upstream_colors)))
+            .collect();
+
+        let mut cells = Vec::new();
+        if let Some(underline) = &self.styling.conditional_underline {
+            for (row_idx, row) in rows.iter().enumerate() {
+                for (day_idx, cell) in row.iter().enumerate() {
+                    let Some(weekday) = days.get(day_idx).map(|day| day.weekday) else {
+                        continue;
+                    };
+                    if !underline.weekdays.iter().any(|&w| u32::from(w) == weekday) {
+                        continue;
+                    }
+                    for (entry_idx, entry) in cell.iter().flatten().enumerate() {
+                        if entry.code() == underline.shift_code {
+                            cells.push(CellStyle {
+                                row: row_idx,
+                                day: day_idx,
+                                entry: entry_idx,
+                                underline: true,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        ShiftStyles { codes, cells }
+    }
+
+    /// Configured colours first, then the upstream colour, then the generated palette.
+    /// Like the grid, the configured and generated colours go by the display code.
+    fn code_style(&self, code: &str, upstream_colors: &BTreeMap<String, String>) -> CodeStyle {
+        let display_code = self.display.display_code(code);
+        if let Some(color) = self.colors.colors.get(&display_code) {
+            return CodeStyle {
+                background: color.background.clone(),
+                text: color.text.clone(),
+                source: "config",
+            };
+        }
+
+        if let Some((background, text)) = upstream_colors
+            .get(code)
+            .and_then(|color| Some((color.trim(), contrast_text(color)?)))
+        {
+            return CodeStyle {
+                background: background.to_string(),
+                text: text.to_string(),
+                source: "upstream",
+            };
+        }
+
+        let (background, text) = generated_color(&display_code, &self.colors.fallback);
+        CodeStyle {
+            background,
+            text,
+            source: "generated",
+        }
+    }
+}
+
+/// Same palette as `getShiftColorFromConfig` in `src/lib/colors.ts`
+fn generated_color(code: &str, fallback: &ColorFallback) -> (String, String) {
+    let hash = hash_code(code) as f64;
+    let hue = hash % 360.0;
+    let saturation = fallback.saturation_min + hash % fallback.saturation_range.max(1.0);
+    let lightness = fallback.lightness_min + hash % fallback.lightness_range.max(1.0);
+    let text_lightness = (lightness - fallback.text_lightness_offset).max(10.0);
+
+    (
+        format!("hsl({}, {}%, {}%)", hue, saturation, lightness),
+        format!("hsl({}, {}%, {}%)", hue, saturation, text_lightness),
+    )
+}
+
+/// JavaScript's 32-bit string hash over UTF-16 units, as `hashCode` in the frontend
+fn hash_code(value: &str) -> u64 {
+    let mut hash: i32 = 0;
+    for unit in value.encode_utf16() {
+        hash = (hash << 5).wrapping_sub(hash).wrapping_add(i32::from(unit));
+    }
+    i64::from(hash).unsigned_abs()
+}
+
+/// Black or white text for a `#rgb`/`#rrggbb` background; `None` for anything else
+fn contrast_text(background: &str) -> Option<&'static str> {
+    let hex = background.trim().strip_prefix('#')?;
+    let channels: Vec<u8> = match hex.len() {
+        3 => hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|v| (v * 17) as u8))
+            .collect::<Option<_>>()?,
+        6 => (0..3)
+            .map(|i| u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok())
+            .collect::<Option<_>>()?,
+        _ => return None,
+    };
+
+    // WCAG relative luminance
+    let linear = |channel: u8| {
+        let c = f64::from(channel) / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let luminance =
+        0.2126 * linear(channels[0]) + 0.7152 * linear(channels[1]) + 0.0722 * linear(channels[2]);
+
+    Some(if luminance > 0.179 {
+        "#000000"
+    } else {
+        "#ffffff"
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style_config() -> StyleConfig {
+        StyleConfig {
+            colors: serde_json::from_str(
+                r##"{ "colors": {
+                    "N": { "background": "#1e3a8a", "text": "#ffffff" },
+                    "FT": { "background": "#fde68a", "text": "#92400e" }
+                } }"##,
+            )
+            .unwrap(),
+            styling: ShiftStylingConfig::default(),
+            display: ShiftDisplayConfig::from(
+                serde_json::from_str::<RawShiftDisplayConfig>(r#"{ "aliases": { "night": "N" } }"#)
+                    .unwrap(),
+            ),
+        }
+    }
+
+    #[test]
+    fn hash_code_matches_javascript() {
+        // Values from `hashCode` in src/lib/colors.ts
+        for (value, expected) in [
+            ("", 0),
+            ("M", 77),
+            ("RATP", 2508011),
+            ("Pomeriggio", 2099940492),
+            ("Guardia medica", 735718306),
+            ("Guardia è", 1817939941),
+            ("🚑", 1773044),
+        ] {
+            assert_eq!(hash_code(value), expected, "{}", value);
+        }
+    }
+
+    #[test]
+    fn generated_color_matches_the_frontend_palette() {
+        let fallback = ColorFallback::default();
+
+        assert_eq!(
+            generated_color("RATP", &fallback),
+            (
+                "hsl(251, 51%, 86%)".to_string(),
+                "hsl(251, 51%, 21%)".to_string()
+            )
+        );
+        assert_eq!(
+            generated_color("Pomeriggio", &fallback),
+            (
+                "hsl(12, 52%, 87%)".to_string(),
+                "hsl(12, 52%, 22%)".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn contrast_text_picks_black_or_white() {
+        assert_eq!(contrast_text("#000"), Some("#ffffff"));
+        assert_eq!(contrast_text("#1e40af"), Some("#ffffff"));
+        assert_eq!(contrast_text(" #FFFFFF "), Some("#000000"));
+        assert_eq!(contrast_text("#fde68a"), Some("#000000"));
+        for invalid in ["red", "#12345", "#ggg", "1e40af", ""] {
+            assert_eq!(contrast_text(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn code_style_uses_the_display_code() {
+        let config = style_config();
+        let upstream: BTreeMap<String, String> = [("P".to_string(), "#1e40af".to_string())]
+            .into_iter()
+            .collect();
+
+        let festivo = config.code_style("FT (Festivo)", &upstream);
+        assert_eq!(
+            (festivo.background.as_str(), festivo.source),
+            ("#fde68a", "config")
+        );
+        let night = config.code_style("night", &upstream);
+        assert_eq!(
+            (night.background.as_str(), night.source),
+            ("#1e3a8a", "config")
+        );
+
+        let upstream_style = config.code_style("P", &upstream);
+        assert_eq!(
+            (
+                upstream_style.background.as_str(),
+                upstream_style.text.as_str(),
+                upstream_style.source
+            ),
+            ("#1e40af", "#ffffff", "upstream")
+        );
+
+        let generated = config.code_style("RATP 8:00 - 14:00", &upstream);
+        assert_eq!(generated.source, "generated");
+        assert_eq!(generated.background, "hsl(251, 51%, 86%)");
+    }
+}
@@ -0,0 +1,139 @@
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone,
+};
+
+// The roster is kept in Italian local time
+const CET_OFFSET_SECONDS: i32 = 3600;
+const CEST_OFFSET_SECONDS: i32 = 2 * 3600;
+// EU rule: summer time runs from 01:00 UTC on the last Sunday of March
+// to 01:00 UTC on the last Sunday of October
+const DST_SWITCH_HOUR_UTC: u32 = 1;
+
+/// Parse an upstream timestamp into Europe/Rome local time.
+/// Timestamps with an offset (or `Z`) are converted; bare ones are Rome wall-clock time.
+pub fn parse_timestamp(value: &str) -> Option<DateTime<FixedOffset>> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    if let Ok(parsed) = DateTime::parse_from_rfc3339(trimmed) {
+        return Some(to_rome(parsed));
+    }
+    if let Some(parsed) = ["%Y-%m-%d %H:%M:%S%:z", "%Y-%m-%d %H:%M:%S%z"]
+        .iter()
+        .find_map(|format| DateTime::parse_from_str(trimmed, format).ok())
+    {
+        return Some(to_rome(parsed));
+    }
+
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(trimmed, format).ok())
+        .and_then(from_rome_local)
+}
+
+/// RFC 3339 with the Rome offset, e.g. `2025-01-02T20:00:00+01:00`
+pub fn format_timestamp(value: &DateTime<FixedOffset>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::Secs, false)
+}
+
+/// Interpret a wall-clock time in Rome. In the repeated autumn hour the earlier
+/// (summer time) instant wins; times skipped in spring are read as winter time.
+pub fn from_rome_local(local: NaiveDateTime) -> Option<DateTime<FixedOffset>> {
+    for offset_seconds in [CEST_OFFSET_SECONDS, CET_OFFSET_SECONDS] {
+        let offset = FixedOffset::east_opt(offset_seconds)?;
+        let utc = local - Duration::seconds(offset_seconds as i64);
+        if rome_offset(utc)? == offset {
+            return offset.from_local_datetime(&local).single();
+        }
+    }
+
+    FixedOffset::east_opt(CET_OFFSET_SECONDS)?
+        .from_local_datetime(&local)
+        .single()
+}
+
+/// Same instant, shown with the Rome offset in force at that moment
+pub fn to_rome(value: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+    match rome_offset(value.naive_utc()) {
+        Some(offset) => value.with_timezone(&offset),
+        None => value,
+    }
+}
+
+/// UTC offset in Rome at a UTC instant
+fn rome_offset(utc: NaiveDateTime) -> Option<FixedOffset> {
+    let year = utc.year();
+    let summer_start = last_sunday(year, 3)?.and_hms_opt(DST_SWITCH_HOUR_UTC, 0, 0)?;
+    let summer_end = last_sunday(year, 10)?.and_hms_opt(DST_SWITCH_HOUR_UTC, 0, 0)?;
+    let offset_seconds = if utc >= summer_start && utc < summer_end {
+        CEST_OFFSET_SECONDS
+    } else {
+        CET_OFFSET_SECONDS
+    };
+    FixedOffset::east_opt(offset_seconds)
+}
+
+fn last_sunday(year: i32, month: u32) -> Option<NaiveDate> {
+    let last_day = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)?
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)?
+    }
+    .pred_opt()?;
+    Some(last_day - Duration::days(last_day.weekday().num_days_from_sunday() as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rome(value: &str) -> String {
+        format_timestamp(&parse_timestamp(value).expect("valid timestamp"))
+    }
+
+    #[test]
+    fn last_sundays() {
+        assert_eq!(last_sunday(2025, 3), NaiveDate::from_ymd_opt(2025, 3, 30));
+        assert_eq!(last_sunday(2025, 10), NaiveDate::from_ymd_opt(2025, 10, 26));
+        assert_eq!(last_sunday(2024, 12), NaiveDate::from_ymd_opt(2024, 12, 29));
+    }
+
+    #[test]
+    fn bare_times_use_the_offset_in_force() {
+        assert_eq!(rome("2025-01-15 08:00:00"), "2025-01-15T08:00:00+01:00");
+        assert_eq!(rome("2025-07-15 08:00"), "2025-07-15T08:00:00+02:00");
+    }
+
+    #[test]
+    fn spring_transition_day() {
+        assert_eq!(rome("2025-03-30 01:30:00"), "2025-03-30T01:30:00+01:00");
+        // 02:00-03:00 does not exist and is read as winter time
+        assert_eq!(rome("2025-03-30 02:30:00"), "2025-03-30T02:30:00+01:00");
+        assert_eq!(rome("2025-03-30 03:30:00"), "2025-03-30T03:30:00+02:00");
+    }
+
+    #[test]
+    fn autumn_transition_day() {
+        // 02:00-03:00 happens twice; the summer-time one wins
+        assert_eq!(rome("2025-10-26 02:30:00"), "2025-10-26T02:30:00+02:00");
+        assert_eq!(rome("2025-10-26 03:30:00"), "2025-10-26T03:30:00+01:00");
+        // Instants with an offset land on the right side of the switch
+        assert_eq!(rome("2025-10-26T00:30:00Z"), "2025-10-26T02:30:00+02:00");
+        assert_eq!(
+            rome("2025-10-26 01:30:00+00:00"),
+            "2025-10-26T02:30:00+01:00"
+        );
+    }
+
+    #[test]
+    fn night_shifts_across_transitions() {
+        let hours = |start: &str, end: &str| {
+            (parse_timestamp(end).unwrap() - parse_timestamp(start).unwrap()).num_hours()
+        };
+
+        assert_eq!(hours("2025-03-29 20:00:00", "2025-03-30 08:00:00"), 11);
+        assert_eq!(hours("2025-10-25 20:00:00", "2025-10-26 08:00:00"), 13);
+    }
+}
@@ -1,3 +1,22 @@
 pub fn set_panic_hook() {
     // Panic hook setup removed - not currently using console_error_panic_hook feature
 }
+
+pub fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Strong ETag derived from the exact response body
+pub fn strong_etag(body: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    format!("\"{}\"", hex_encode(&Sha256::digest(body)[..16]))
+}
+
+/// Evaluate an `If-None-Match` header against our ETag (weak comparison, RFC 9110 13.1.2)
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = opaque(etag);
+    if_none_match
+        .split(',')
+        .any(|candidate| candidate.trim() == "*" || opaque(candidate) == etag)
+}
@@ -0,0 +1,522 @@
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use worker::*;
+
+use crate::config::{self, RulesConfig, StatsConfig};
+use crate::people::{collect_person_shifts, PersonShift};
+use crate::stats::{month_dates, round_hours};
+use crate::timezone;
+use crate::{
+    build_success_response, check_version_access, error_response, get_config, groups, is_valid_ym,
+    query_param, range, response_ttl, restrict_to_version_audience, Person, ScheduleVersion,
+};
+
+// Neighbouring days loaded so rest periods, night runs and ISO weeks that cross
+// the month boundary are judged on the full picture
+const CONTEXT_DAYS: i64 = 7;
+
+#[derive(Serialize)]
+struct Violation {
+    date: String,
+    rule: &'static str,
+    message: String,
+    value: f64,
+    limit: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    week: Option<String>,
+    shifts: Vec<PersonShift>,
+}
+
+#[derive(Serialize)]
+struct PersonViolations {
+    person: Person,
+    violations: Vec<Violation>,
+}
+
+#[derive(Serialize)]
+struct ViolationsReport {
+    ym: String,
+    version: &'static str,
+    rules: RulesConfig,
+    #[serde(rename = "nightCodes")]
+    night_codes: Vec<String>,
+    people: Vec<PersonViolations>,
+    total: usize,
+}
+
+/// A shift with parsed upstream times
+struct Interval<'a> {
+    start: DateTime<FixedOffset>,
+    end: DateTime<FixedOffset>,
+    shift: &'a PersonShift,
+}
+
+/// API endpoint: working-time rule violations for one month, per person and day
+pub async fn handle_shift_violations(req: Request, ctx: RouteContext<Context>) -> Result<Response> {
+    let config = match get_config(&ctx.env) {
+        Ok(cfg) => cfg,
+        Err(e) => return error_response("CONFIG_ERROR", &e.to_string(), 500),
+    };
+
+    let url = req.url()?;
+    let ym = match query_param(&url, "ym") {
+        Some(v) => v,
+        None => return error_response("MISSING_PARAM", "Missing required parameter: ym", 400),
+    };
+    if !is_valid_ym(&ym) {
+        return error_response("INVALID_YM", "Invalid ym format. Expected YYYY-MM", 400);
+    }
+    let Some((month_start, month_end)) = month_dates(&ym) else {
+        return error_response("INVALID_YM", "Invalid ym format. Expected YYYY-MM", 400);
+    };
+
+    let version = match ScheduleVersion::from_query(query_param(&url, "version").as_deref()) {
+        Some(version) => version,
+        None => {
+            return error_response(
+                "INVALID_VERSION",
+                "Invalid version value. Expected live, draft or published",
+                400,
+            )
+        }
+    };
+    if let Some(denied) = check_version_access(&req, &ctx, version) {
+        return denied;
+    }
+
+    let rules: RulesConfig = config::load_json_config(&ctx.env, config::CONFIG_RULES).await;
+    let stats_config: StatsConfig = config::load_json_config(&ctx.env, config::CONFIG_STATS).await;
+
+    let start = month_start - Duration::days(CONTEXT_DAYS);
+    let end = month_end + Duration::days(CONTEXT_DAYS);
+    let months = range::months_in_range(start, end);
+    let results = range::load_months(&ctx, &config, &months, version).await;
+
+    let mut statuses: HashSet<&'static str> = HashSet::new();
+    let mut people: Vec<Person> = Vec::new();
+    let mut roster_groups = Vec::new();
+    let mut shifts_by_person: HashMap<String, Vec<PersonShift>> = HashMap::new();
+    for (month, result) in months.iter().zip(results) {
+        let (month_shifts, status) = match result {
+            Ok(result) => result,
+            Err(failure) => return failure.into_response(),
+        };
+        statuses.insert(status);
+        if *month == ym {
+            roster_groups = month_shifts.groups.clone();
+        }
+
+        for person in &month_shifts.people {
+            if let Some((found, shifts)) =
+                collect_person_shifts(&month_shifts, &person.id, start, end)
+            {
+                // Only people on this month's roster are reported
+                if *month == ym && !people.iter().any(|p| p.id == found.id) {
+                    people.push(found.clone());
+                }
+                shifts_by_person.entry(found.id).or_default().extend(shifts);
+            }
+        }
+    }
+
+    let in_month = |date: &str| {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map(|date| date >= month_start && date <= month_end)
+            .unwrap_or(false)
+    };
+
+    // Same order as the grid
+    groups::sort_people(&mut people, &roster_groups);
+
+    let mut report_people = Vec::new();
+    for person in people {
+        let shifts = shifts_by_person.remove(&person.id).unwrap_or_default();
+        let mut violations: Vec<Violation> = check_person(&shifts, &rules, &stats_config)
+            .into_iter()
+            .filter(|violation| in_month(&violation.date))
+            .collect();
+        if violations.is_empty() {
+            continue;
+        }
+        violations.sort_by(|a, b| a.date.cmp(&b.date).then(a.rule.cmp(b.rule)));
+        report_people.push(PersonViolations { person, violations });
+    }
+
+    let report = ViolationsReport {
+        ym,
+        version: version.as_str(),
+        total: report_people.iter().map(|p| p.violations.len()).sum(),
+        night_codes: stats_config.night_codes.clone(),
+        rules,
+        people: report_people,
+    };
+
+    let cache_status = if statuses.contains("STALE") {
+        "STALE"
+    } else if statuses.len() == 1 && statuses.contains("HIT") {
+        "HIT"
+    } else {
+        "MISS"
+    };
+
+    let json = serde_json::to_string(&report)?;
+    let if_none_match = req.headers().get("If-None-Match")?;
+    let response = build_success_response(
+        json,
+        response_ttl(&config, cache_status),
+        cache_status,
+        if_none_match.as_deref(),
+    )?;
+    restrict_to_version_audience(response, version, &req)
+}
+
+/// Run every rule over one person's shifts. Overlap, rest and weekly hours need
+/// upstream times, so untimed shifts only count towards consecutive nights.
+fn check_person(
+    shifts: &[PersonShift],
+    rules: &RulesConfig,
+    stats_config: &StatsConfig,
+) -> Vec<Violation> {
+    let mut intervals: Vec<Interval> = shifts
+        .iter()
+        .filter_map(|shift| {
+            let start = parse_shift_time(shift.start.as_deref())?;
+            let end = parse_shift_time(shift.end.as_deref())?;
+            Some(Interval {
+                start,
+                end: end.max(start),
+                shift,
+            })
+        })
+        .collect();
+    intervals.sort_by_key(|interval| interval.start);
+
+    let mut violations = check_overlaps(&intervals);
+    if rules.min_rest_hours > 0.0 {
+        violations.extend(check_rest(&intervals, rules.min_rest_hours));
+    }
+    if rules.max_consecutive_nights > 0 {
+        violations.extend(check_consecutive_nights(
+            shifts,
+            rules.max_consecutive_nights,
+            stats_config,
+        ));
+    }
+    if rules.max_weekly_hours > 0.0 {
+        violations.extend(check_weekly_hours(&intervals, rules.max_weekly_hours));
+    }
+    violations
+}
+
+fn check_overlaps(intervals: &[Interval]) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let mut latest: Option<&Interval> = None;
+    for interval in intervals {
+        if let Some(previous) = latest {
+            if interval.start < previous.end {
+                let overlap = previous.end.min(interval.end) - interval.start;
+                violations.push(Violation {
+                    date: interval.shift.date.clone(),
+                    rule: "overlap",
+                    message: format!(
+                        "{} overlaps {} from {}",
+                        interval.shift.code, previous.shift.code, previous.shift.date
+                    ),
+                    value: minutes_to_hours(overlap.num_minutes()),
+                    limit: 0.0,
+                    week: None,
+                    shifts: vec![previous.shift.clone(), interval.shift.clone()],
+                });
+            }
+        }
+        if latest.is_none_or(|previous| interval.end > previous.end) {
+            latest = Some(interval);
+        }
+    }
+    violations
+}
+
+/// Rest is measured between work periods: overlapping or back-to-back shifts
+/// count as one continuous stretch of work.
+fn check_rest(intervals: &[Interval], min_rest_hours: f64) -> Vec<Violation> {
+    let mut periods: Vec<(DateTime<FixedOffset>, &Interval, &Interval)> = Vec::new();
+    for interval in intervals {
+        match periods.last_mut() {
+            Some((end, _, last)) if interval.start <= *end => {
+                if interval.end > *end {
+                    *end = interval.end;
+                    *last = interval;
+                }
+            }
+            _ => periods.push((interval.end, interval, interval)),
+        }
+    }
+
+    let mut violations = Vec::new();
+    for pair in periods.windows(2) {
+        let (previous_end, _, previous) = pair[0];
+        let (_, next, _) = pair[1];
+        let rest_hours = minutes_to_hours((next.start - previous_end).num_minutes());
+        if rest_hours < min_rest_hours {
+            violations.push(Violation {
+                date: next.shift.date.clone(),
+                rule: "rest",
+                message: format!(
+                    "{}h rest between {} ({}) and {}",
+                    rest_hours, previous.shift.code, previous.shift.date, next.shift.code
+                ),
+                value: rest_hours,
+                limit: min_rest_hours,
+                week: None,
+                shifts: vec![previous.shift.clone(), next.shift.clone()],
+            });
+        }
+    }
+    violations
+}
+
+/// One violation per run of nights, dated on the first night over the limit
+fn check_consecutive_nights(
+    shifts: &[PersonShift],
+    max_nights: u32,
+    stats_config: &StatsConfig,
+) -> Vec<Violation> {
+    let nights: BTreeMap<NaiveDate, &PersonShift> = shifts
+        .iter()
+        .filter(|shift| stats_config.is_night(&shift.code))
+        .filter_map(|shift| {
+            let date = NaiveDate::parse_from_str(&shift.date, "%Y-%m-%d").ok()?;
+            Some((date, shift))
+        })
+        .collect();
+
+    let mut runs: Vec<Vec<(NaiveDate, &PersonShift)>> = Vec::new();
+    for (date, shift) in nights {
+        match runs.last_mut() {
+            Some(run) if run.last().and_then(|(last, _)| last.succ_opt()) == Some(date) => {
+                run.push((date, shift))
+            }
+            _ => runs.push(vec![(date, shift)]),
+        }
+    }
+
+    runs.into_iter()
+        .filter(|run| run.len() > max_nights as usize)
+        .map(|run| {
+            let (first_over, _) = run[max_nights as usize];
+            let (first, _) = run[0];
+            Violation {
+                date: first_over.format("%Y-%m-%d").to_string(),
+                rule: "consecutiveNights",
+                message: format!(
+                    "{} consecutive nights from {}",
+                    run.len(),
+                    first.format("%Y-%m-%d")
+                ),
+                value: run.len() as f64,
+                limit: max_nights as f64,
+                week: None,
+                shifts: run.iter().map(|(_, shift)| (*shift).clone()).collect(),
+            }
+        })
+        .collect()
+}
+
+/// Hours per ISO week (by roster day), dated on the shift that crosses the limit
+fn check_weekly_hours(intervals: &[Interval], max_weekly_hours: f64) -> Vec<Violation> {
+    let mut weeks: BTreeSet<(i32, u32)> = BTreeSet::new();
+    let mut by_week: HashMap<(i32, u32), Vec<&Interval>> = HashMap::new();
+    for interval in intervals {
+        let Ok(date) = NaiveDate::parse_from_str(&interval.shift.date, "%Y-%m-%d") else {
+            continue;
+        };
+        let week = date.iso_week();
+        let key = (week.year(), week.week());
+        weeks.insert(key);
+        by_week.entry(key).or_default().push(interval);
+    }
+
+    let mut violations = Vec::new();
+    for key in weeks {
+        let week_intervals = &by_week[&key];
+        let mut total_minutes = 0;
+        let mut crossed_on: Option<&Interval> = None;
+        for interval in week_intervals {
+            total_minutes += (interval.end - interval.start).num_minutes();
+            if crossed_on.is_none() && minutes_to_hours(total_minutes) > max_weekly_hours {
+                crossed_on = Some(interval);
+            }
+        }
+
+        if let Some(interval) = crossed_on {
+            let total_hours = minutes_to_hours(total_minutes);
+            let week = format!("{}-W{:02}", key.0, key.1);
+            violations.push(Violation {
+                date: interval.shift.date.clone(),
+                rule: "weeklyHours",
+                message: format!("{}h scheduled in {}", total_hours, week),
+                value: total_hours,
+                limit: max_weekly_hours,
+                week: Some(week),
+                shifts: week_intervals
+                    .iter()
+                    .map(|interval| interval.shift.clone())
+                    .collect(),
+            });
+        }
+    }
+    violations
+}
+
+fn minutes_to_hours(minutes: i64) -> f64 {
+    round_hours(minutes as f64 / 60.0)
+}
+
+fn parse_shift_time(value: Option<&str>) -> Option<DateTime<FixedOffset>> {
+    timezone::parse_timestamp(value?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shift(date: &str, code: &str, start: &str, end: &str) -> PersonShift {
+        PersonShift {
+            date: date.to_string(),
+            code: code.to_string(),
+            label: code.to_string(),
+            start: Some(start.to_string()),
+            end: Some(end.to_string()),
+            duration_minutes: None,
+        }
+    }
+
+    fn rules(
+        min_rest_hours: f64,
+        max_consecutive_nights: u32,
+        max_weekly_hours: f64,
+    ) -> RulesConfig {
+        RulesConfig {
+            min_rest_hours,
+            max_consecutive_nights,
+            max_weekly_hours,
+        }
+    }
+
+    fn stats_config() -> StatsConfig {
+        StatsConfig {
+            night_codes: vec!["N".to_string()],
+        }
+    }
+
+    fn summary(violations: &[Violation]) -> Vec<(&'static str, &str, f64)> {
+        violations
+            .iter()
+            .map(|violation| (violation.rule, violation.date.as_str(), violation.value))
+            .collect()
+    }
+
+    #[test]
+    fn overlapping_shifts_are_reported_once() {
+        let shifts = [
+            shift("2025-01-06", "M", "2025-01-06 08:00", "2025-01-06 14:00"),
+            shift("2025-01-06", "P", "2025-01-06 13:00", "2025-01-06 20:00"),
+        ];
+
+        let violations = check_person(&shifts, &rules(0.0, 0, 0.0), &stats_config());
+
+        assert_eq!(summary(&violations), [("overlap", "2025-01-06", 1.0)]);
+        assert_eq!(violations[0].shifts.len(), 2);
+    }
+
+    #[test]
+    fn short_rest_between_work_periods() {
+        let shifts = [
+            shift("2025-01-06", "P", "2025-01-06 14:00", "2025-01-06 20:00"),
+            shift("2025-01-07", "M", "2025-01-07 06:00", "2025-01-07 14:00"),
+        ];
+
+        let violations = check_person(&shifts, &rules(11.0, 0, 0.0), &stats_config());
+
+        assert_eq!(summary(&violations), [("rest", "2025-01-07", 10.0)]);
+    }
+
+    #[test]
+    fn back_to_back_shifts_are_one_work_period() {
+        let shifts = [
+            shift("2025-01-06", "M", "2025-01-06 08:00", "2025-01-06 14:00"),
+            shift("2025-01-06", "P", "2025-01-06 14:00", "2025-01-06 20:00"),
+            shift("2025-01-07", "M", "2025-01-07 08:00", "2025-01-07 14:00"),
+        ];
+
+        let violations = check_person(&shifts, &rules(11.0, 0, 0.0), &stats_config());
+
+        assert!(violations.is_empty());
+    }
+
+    #[test]
+    fn consecutive_nights_over_the_limit() {
+        let nights: Vec<PersonShift> = (6..=9)
+            .map(|day| {
+                shift(
+                    &format!("2025-01-{:02}", day),
+                    "N",
+                    &format!("2025-01-{:02} 20:00", day),
+                    &format!("2025-01-{:02} 08:00", day + 1),
+                )
+            })
+            .collect();
+
+        let violations = check_person(&nights, &rules(0.0, 3, 0.0), &stats_config());
+        assert_eq!(
+            summary(&violations),
+            [("consecutiveNights", "2025-01-09", 4.0)]
+        );
+        assert_eq!(violations[0].shifts.len(), 4);
+
+        // A free night starts a new run
+        let broken: Vec<PersonShift> = nights
+            .into_iter()
+            .filter(|night| night.date != "2025-01-08")
+            .collect();
+        assert!(check_person(&broken, &rules(0.0, 3, 0.0), &stats_config()).is_empty());
+    }
+
+    #[test]
+    fn weekly_hours_dated_on_the_shift_that_crosses_the_limit() {
+        let shifts: Vec<PersonShift> = (6..=10)
+            .map(|day| {
+                shift(
+                    &format!("2025-01-{:02}", day),
+                    "L",
+                    &format!("2025-01-{:02} 08:00", day),
+                    &format!("2025-01-{:02} 18:00", day),
+                )
+            })
+            .collect();
+
+        let violations = check_person(&shifts, &rules(0.0, 0, 48.0), &stats_config());
+
+        assert_eq!(summary(&violations), [("weeklyHours", "2025-01-10", 50.0)]);
+        assert_eq!(violations[0].week.as_deref(), Some("2025-W02"));
+    }
+
+    #[test]
+    fn zero_limits_turn_rules_off() {
+        let shifts = [
+            shift("2025-01-06", "N", "2025-01-06 20:00", "2025-01-07 08:00"),
+            shift("2025-01-07", "M", "2025-01-07 10:00", "2025-01-07 14:00"),
+        ];
+
+        assert!(check_person(&shifts, &rules(0.0, 0, 0.0), &stats_config()).is_empty());
+        assert_eq!(
+            summary(&check_person(
+                &shifts,
+                &rules(11.0, 0, 0.0),
+                &stats_config()
+            )),
+            [("rest", "2025-01-07", 2.0)]
+        );
+    }
+}
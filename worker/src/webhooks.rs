@@ -0,0 +1,201 @@
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use worker::*;
+
+use crate::changes::{self, ScheduleDiff};
+use crate::{
+    archive_snapshot, get_config, offset_ym, refresh_month_shifts, snapshots, utils, MonthShifts,
+    ScheduleVersion,
+};
+
+const SIGNATURE_HEADER: &str = "X-Schedule-Signature";
+const TIMESTAMP_HEADER: &str = "X-Schedule-Timestamp";
+
+#[derive(Serialize)]
+struct ScheduleChangedPayload<'a> {
+    event: &'static str,
+    ym: &'a str,
+    #[serde(rename = "detectedAt")]
+    detected_at: String,
+    #[serde(flatten)]
+    diff: &'a ScheduleDiff,
+}
+
+/// Cron job: refresh the current and next month and notify webhooks about changes
+pub async fn poll_schedule_changes(env: &Env) -> Result<()> {
+    let config = get_config(env)?;
+    let current_ym = Utc::now().format("%Y-%m").to_string();
+    let months: Vec<String> = [0, 1]
+        .iter()
+        .filter_map(|offset| offset_ym(&current_ym, *offset))
+        .collect();
+
+    for ym in months {
+        let baseline = load_baseline(env, &ym).await;
+
+        let fresh = match refresh_month_shifts(env, &config, &ym, ScheduleVersion::Live).await {
+            Ok(month_shifts) => {
+                archive_snapshot(env, &month_shifts, ScheduleVersion::Live).await;
+                month_shifts
+            }
+            Err(failure) => {
+                console_error!("Schedule poll for {} failed: {}", ym, failure.message());
+                continue;
+            }
+        };
+
+        match &baseline {
+            None => console_log!(
+                "Schedule poll for {}: no baseline yet, storing first version",
+                ym
+            ),
+            Some(baseline) => {
+                let diff = changes::diff_month_shifts(baseline, &fresh);
+                if diff.is_empty() {
+                    console_log!("Schedule poll for {}: no changes", ym);
+                } else if let Err(error) = notify_schedule_change(env, &ym, &diff).await {
+                    // Keep the old state so the next run diffs against it and sends again
+                    console_error!(
+                        "Schedule change for {} not delivered, will retry: {:?}",
+                        ym,
+                        error
+                    );
+                    continue;
+                }
+            }
+        }
+
+        if let Err(error) = snapshots::store_poll_state(env, &fresh).await {
+            console_error!("Failed to store poll state for {}: {:?}", ym, error);
+        }
+    }
+
+    Ok(())
+}
+
+/// What the poller last saw for a month. Viewer requests keep moving the newest
+/// snapshot, so the poller keeps its own state in R2 and only falls back to it.
+async fn load_baseline(env: &Env, ym: &str) -> Option<MonthShifts> {
+    match snapshots::load_poll_state(env, ym).await {
+        Ok(Some(state)) => return Some(state),
+        Ok(None) => {}
+        Err(error) => console_error!("Failed to load poll state for {}: {:?}", ym, error),
+    }
+
+    match snapshots::load_latest_snapshot(env, ym).await {
+        Ok(snapshot) => snapshot,
+        Err(error) => {
+            console_error!("Failed to load baseline snapshot for {}: {:?}", ym, error);
+            None
+        }
+    }
+}
+
+fn resolve_webhook_urls(env: &Env) -> Vec<String> {
+    // Chat webhook URLs usually embed a token, so a secret takes precedence over a var
+    let raw = env
+        .secret("SCHEDULE_WEBHOOK_URLS")
+        .map(|value| value.to_string())
+        .or_else(|_| {
+            env.var("SCHEDULE_WEBHOOK_URLS")
+                .map(|value| value.to_string())
+        })
+        .unwrap_or_default();
+
+    raw.split(|c: char| c == ',' || c.is_whitespace())
+        .map(|url| url.trim())
+        .filter(|url| !url.is_empty())
+        .map(|url| url.to_string())
+        .collect()
+}
+
+/// HMAC-SHA256 over "<timestamp>.<body>", hex encoded
+fn sign_payload(secret: &str, timestamp: i64, body: &str) -> Result<String> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .map_err(|_| Error::RustError("Invalid webhook secret".to_string()))?;
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    Ok(utils::hex_encode(&mac.finalize().into_bytes()))
+}
+
+/// Send the change to every configured URL. Fails unless all of them accepted it,
+/// so the poller can keep its state and resend on the next run.
+async fn notify_schedule_change(env: &Env, ym: &str, diff: &ScheduleDiff) -> Result<()> {
+    let urls = resolve_webhook_urls(env);
+    if urls.is_empty() {
+        console_log!(
+            "Schedule change for {} detected but no SCHEDULE_WEBHOOK_URLS configured",
+            ym
+        );
+        return Ok(());
+    }
+
+    let secret = env.secret("SCHEDULE_WEBHOOK_SECRET").map_err(|_| {
+        Error::RustError(
+            "SCHEDULE_WEBHOOK_SECRET not configured, refusing to send unsigned webhooks"
+                .to_string(),
+        )
+    })?;
+    let secret = secret.to_string();
+
+    let now = Utc::now();
+    let payload = ScheduleChangedPayload {
+        event: "schedule.changed",
+        ym,
+        detected_at: now.to_rfc3339(),
+        diff,
+    };
+    let body = serde_json::to_string(&payload)
+        .map_err(|e| Error::RustError(format!("Failed to serialize webhook payload: {}", e)))?;
+
+    let timestamp = now.timestamp();
+    let signature = sign_payload(&secret, timestamp, &body)?;
+
+    let mut failed = 0;
+    for url in &urls {
+        if let Err(e) = post_webhook(url, &body, timestamp, &signature).await {
+            console_error!("Webhook delivery to {} failed: {:?}", url, e);
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        return Err(Error::RustError(format!(
+            "{} of {} webhook deliveries failed",
+            failed,
+            urls.len()
+        )));
+    }
+    Ok(())
+}
+
+async fn post_webhook(url: &str, body: &str, timestamp: i64, signature: &str) -> Result<()> {
+    let headers = Headers::new();
+    headers.set("Content-Type", "application/json")?;
+    headers.set(TIMESTAMP_HEADER, &timestamp.to_string())?;
+    headers.set(SIGNATURE_HEADER, &format!("sha256={}", signature))?;
+
+    let mut request_init = RequestInit::new();
+    request_init
+        .with_method(Method::Post)
+        .with_headers(headers)
+        .with_body(Some(body.to_string().into()));
+
+    let request = Request::new_with_init(url, &request_init)?;
+    let mut response = Fetch::Request(request).send().await?;
+
+    let status = response.status_code();
+    if !(200..300).contains(&status) {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(Error::RustError(format!(
+            "Webhook returned {} - {}",
+            status, error_text
+        )));
+    }
+
+    console_log!("Webhook delivered to {} ({})", url, status);
+    Ok(())
+}
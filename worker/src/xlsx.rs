@@ -0,0 +1,387 @@
+// Minimal SpreadsheetML writer: text cells only, bold header row, frozen panes.
+// The package is an uncompressed ZIP, which every spreadsheet app accepts.
+
+const HEADER_STYLE: usize = 1;
+const MIN_COLUMN_WIDTH: usize = 6;
+const MAX_COLUMN_WIDTH: usize = 40;
+
+pub struct Sheet {
+    /// At most 31 characters, without `[]:*?/\`
+    pub name: String,
+    /// The first row is the header and is shown in bold
+    pub rows: Vec<Vec<String>>,
+    pub frozen_rows: usize,
+    pub frozen_columns: usize,
+}
+
+/// Build an `.xlsx` workbook with one worksheet per sheet
+pub fn write_workbook(sheets: &[Sheet]) -> Vec<u8> {
+    let mut files: Vec<(String, String)> = vec![
+        (
+            "[Content_Types].xml".to_string(),
+            content_types(sheets.len()),
+        ),
+        ("_rels/.rels".to_string(), ROOT_RELS.to_string()),
+        ("xl/workbook.xml".to_string(), workbook(sheets)),
+        (
+            "xl/_rels/workbook.xml.rels".to_string(),
+            workbook_rels(sheets.len()),
+        ),
+        ("xl/styles.xml".to_string(), STYLES.to_string()),
+    ];
+    for (idx, sheet) in sheets.iter().enumerate() {
+        files.push((
+            format!("xl/worksheets/sheet{}.xml", idx + 1),
+            worksheet(sheet),
+        ));
+    }
+
+    let mut zip = ZipWriter::default();
+    for (name, content) in &files {
+        zip.add(name, content.as_bytes());
+    }
+    zip.finish()
+}
+
+const ROOT_RELS: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+    r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+    r#"<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/>"#,
+    r#"</Relationships>"#,
+);
+
+// Style 0 is the default; style 1 is bold (the header row)
+const STYLES: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+    r#"<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">"#,
+    r#"<fonts count="2"><font><sz val="11"/><name val="Calibri"/></font><font><b/><sz val="11"/><name val="Calibri"/></font></fonts>"#,
+    r#"<fills count="2"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill></fills>"#,
+    r#"<borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders>"#,
+    r#"<cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs>"#,
+    r#"<cellXfs count="2"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="0" fontId="1" fillId="0" borderId="0" xfId="0" applyFont="1"/></cellXfs>"#,
+    r#"</styleSheet>"#,
+);
+
+fn content_types(sheet_count: usize) -> String {
+    let mut xml = String::from(concat!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+        r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#,
+        r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#,
+        r#"<Default Extension="xml" ContentType="application/xml"/>"#,
+        r#"<Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>"#,
+        r#"<Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/>"#,
+    ));
+    for idx in 1..=sheet_count {
+        xml.push_str(&format!(
+            r#"<Override PartName="/xl/worksheets/sheet{}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#,
+            idx
+        ));
+    }
+    xml.push_str("</Types>");
+    xml
+}
+
+fn workbook(sheets: &[Sheet]) -> String {
+    let mut xml = String::from(concat!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+        r#"<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" "#,
+        r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets>"#,
+    ));
+    for (idx, sheet) in sheets.iter().enumerate() {
+        xml.push_str(&format!(
+            r#"<sheet name="{}" sheetId="{}" r:id="rId{}"/>"#,
+            escape_xml(&sheet.name),
+            idx + 1,
+            idx + 1
+        ));
+    }
+    xml.push_str("</sheets></workbook>");
+    xml
+}
+
+fn workbook_rels(sheet_count: usize) -> String {
+    let mut xml = String::from(concat!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+        r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+    ));
+    for idx in 1..=sheet_count {
+        xml.push_str(&format!(
+            r#"<Relationship Id="rId{}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet{}.xml"/>"#,
+            idx, idx
+        ));
+    }
+    xml.push_str(&format!(
+        r#"<Relationship Id="rId{}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/>"#,
+        sheet_count + 1
+    ));
+    xml.push_str("</Relationships>");
+    xml
+}
+
+fn worksheet(sheet: &Sheet) -> String {
+    let mut xml = String::from(concat!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+        r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">"#,
+    ));
+
+    if sheet.frozen_rows > 0 || sheet.frozen_columns > 0 {
+        let top_left = cell_ref(sheet.frozen_rows, sheet.frozen_columns);
+        xml.push_str(r#"<sheetViews><sheetView workbookViewId="0"><pane "#);
+        if sheet.frozen_columns > 0 {
+            xml.push_str(&format!(r#"xSplit="{}" "#, sheet.frozen_columns));
+        }
+        if sheet.frozen_rows > 0 {
+            xml.push_str(&format!(r#"ySplit="{}" "#, sheet.frozen_rows));
+        }
+        let active_pane = match (sheet.frozen_rows > 0, sheet.frozen_columns > 0) {
+            (true, true) => "bottomRight",
+            (true, false) => "bottomLeft",
+            _ => "topRight",
+        };
+        xml.push_str(&format!(
+            r#"topLeftCell="{}" activePane="{}" state="frozen"/></sheetView></sheetViews>"#,
+            top_left, active_pane
+        ));
+    }
+
+    let widths = column_widths(&sheet.rows);
+    if !widths.is_empty() {
+        xml.push_str("<cols>");
+        for (idx, width) in widths.iter().enumerate() {
+            xml.push_str(&format!(
+                r#"<col min="{}" max="{}" width="{}" customWidth="1"/>"#,
+                idx + 1,
+                idx + 1,
+                width
+            ));
+        }
+        xml.push_str("</cols>");
+    }
+
+    xml.push_str("<sheetData>");
+    for (row_idx, row) in sheet.rows.iter().enumerate() {
+        xml.push_str(&format!(r#"<row r="{}">"#, row_idx + 1));
+        for (col_idx, value) in row.iter().enumerate() {
+            if value.is_empty() {
+                continue;
+            }
+            let style = if row_idx == 0 {
+                format!(r#" s="{}""#, HEADER_STYLE)
+            } else {
+                String::new()
+            };
+            xml.push_str(&format!(
+                r#"<c r="{}"{} t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                cell_ref(row_idx, col_idx),
+                style,
+                escape_xml(value)
+            ));
+        }
+        xml.push_str("</row>");
+    }
+    xml.push_str("</sheetData></worksheet>");
+    xml
+}
+
+/// Widest value per column, in characters, within sensible bounds
+fn column_widths(rows: &[Vec<String>]) -> Vec<usize> {
+    let mut widths: Vec<usize> = Vec::new();
+    for row in rows {
+        for (idx, value) in row.iter().enumerate() {
+            let width = value.chars().count() + 2;
+            match widths.get_mut(idx) {
+                Some(existing) => *existing = (*existing).max(width),
+                None => widths.push(width),
+            }
+        }
+    }
+    widths
+        .into_iter()
+        .map(|width| width.clamp(MIN_COLUMN_WIDTH, MAX_COLUMN_WIDTH))
+        .collect()
+}
+
+/// Zero-based row and column to an A1 reference
+fn cell_ref(row: usize, column: usize) -> String {
+    let mut letters = Vec::new();
+    let mut remaining = column + 1;
+    while remaining > 0 {
+        let rem = (remaining - 1) % 26;
+        letters.push((b'A' + rem as u8) as char);
+        remaining = (remaining - 1) / 26;
+    }
+    letters.reverse();
+    format!("{}{}", letters.into_iter().collect::<String>(), row + 1)
+}
+
+/// Escape text for XML, dropping control characters XML 1.0 cannot carry
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Store-only ZIP archive with a fixed timestamp, so identical content gives identical bytes
+#[derive(Default)]
+struct ZipWriter {
+    buffer: Vec<u8>,
+    central_directory: Vec<u8>,
+    entries: u16,
+}
+
+// 1980-01-01 00:00, the MS-DOS epoch
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+impl ZipWriter {
+    fn add(&mut self, name: &str, data: &[u8]) {
+        let offset = self.buffer.len() as u32;
+        let crc = crc32(data);
+        let size = data.len() as u32;
+
+        // Local file header
+        self.buffer.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        self.buffer.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        self.buffer.extend_from_slice(&0u16.to_le_bytes()); // flags
+        self.buffer.extend_from_slice(&0u16.to_le_bytes()); // method: stored
+        self.buffer.extend_from_slice(&DOS_TIME.to_le_bytes());
+        self.buffer.extend_from_slice(&DOS_DATE.to_le_bytes());
+        self.buffer.extend_from_slice(&crc.to_le_bytes());
+        self.buffer.extend_from_slice(&size.to_le_bytes()); // compressed
+        self.buffer.extend_from_slice(&size.to_le_bytes()); // uncompressed
+        self.buffer
+            .extend_from_slice(&(name.len() as u16).to_le_bytes());
+        self.buffer.extend_from_slice(&0u16.to_le_bytes()); // extra length
+        self.buffer.extend_from_slice(name.as_bytes());
+        self.buffer.extend_from_slice(data);
+
+        // Central directory entry
+        let cd = &mut self.central_directory;
+        cd.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        cd.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        cd.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        cd.extend_from_slice(&0u16.to_le_bytes()); // flags
+        cd.extend_from_slice(&0u16.to_le_bytes()); // method: stored
+        cd.extend_from_slice(&DOS_TIME.to_le_bytes());
+        cd.extend_from_slice(&DOS_DATE.to_le_bytes());
+        cd.extend_from_slice(&crc.to_le_bytes());
+        cd.extend_from_slice(&size.to_le_bytes());
+        cd.extend_from_slice(&size.to_le_bytes());
+        cd.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        cd.extend_from_slice(&0u16.to_le_bytes()); // extra length
+        cd.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        cd.extend_from_slice(&0u16.to_le_bytes()); // disk number
+        cd.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+        cd.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+        cd.extend_from_slice(&offset.to_le_bytes());
+        cd.extend_from_slice(name.as_bytes());
+
+        self.entries += 1;
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let cd_offset = self.buffer.len() as u32;
+        let cd_size = self.central_directory.len() as u32;
+        self.buffer.append(&mut self.central_directory);
+
+        // End of central directory record
+        self.buffer.extend_from_slice(&0x06054b50u32.to_le_bytes());
+        self.buffer.extend_from_slice(&0u16.to_le_bytes()); // this disk
+        self.buffer.extend_from_slice(&0u16.to_le_bytes()); // central directory disk
+        self.buffer.extend_from_slice(&self.entries.to_le_bytes());
+        self.buffer.extend_from_slice(&self.entries.to_le_bytes());
+        self.buffer.extend_from_slice(&cd_size.to_le_bytes());
+        self.buffer.extend_from_slice(&cd_offset.to_le_bytes());
+        self.buffer.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        self.buffer
+    }
+}
+
+/// CRC-32 (IEEE), as ZIP requires
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sheet(rows: &[&[&str]]) -> Sheet {
+        Sheet {
+            name: "Turni".to_string(),
+            rows: rows
+                .iter()
+                .map(|row| row.iter().map(|value| value.to_string()).collect())
+                .collect(),
+            frozen_rows: 1,
+            frozen_columns: 1,
+        }
+    }
+
+    #[test]
+    fn cell_refs_use_bijective_column_letters() {
+        assert_eq!(cell_ref(0, 0), "A1");
+        assert_eq!(cell_ref(0, 25), "Z1");
+        assert_eq!(cell_ref(0, 26), "AA1");
+        assert_eq!(cell_ref(9, 701), "ZZ10");
+        assert_eq!(cell_ref(0, 702), "AAA1");
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn escape_xml_drops_invalid_control_characters() {
+        assert_eq!(
+            escape_xml("<M & \"P\">\u{1}\tN"),
+            "&lt;M &amp; &quot;P&quot;&gt;\tN"
+        );
+    }
+
+    #[test]
+    fn worksheet_freezes_the_header_and_skips_empty_cells() {
+        let xml = worksheet(&sheet(&[&["Nome", "Mer 01"], &["Rossi", ""]]));
+
+        assert!(xml.contains(
+            r#"<pane xSplit="1" ySplit="1" topLeftCell="B2" activePane="bottomRight" state="frozen"/>"#
+        ));
+        assert!(xml.contains(r#"<c r="B1" s="1" t="inlineStr">"#));
+        assert!(xml.contains(r#"<c r="A2" t="inlineStr">"#));
+        assert!(!xml.contains(r#"r="B2""#));
+    }
+
+    #[test]
+    fn workbook_is_a_zip_with_one_entry_per_part() {
+        let bytes = write_workbook(&[sheet(&[&["Nome"]]), sheet(&[&["Codice"]])]);
+
+        assert_eq!(bytes[..4], [0x50, 0x4b, 0x03, 0x04]);
+        // End of central directory record: 22 bytes with no comment
+        let eocd = &bytes[bytes.len() - 22..];
+        assert_eq!(eocd[..4], 0x06054b50u32.to_le_bytes());
+        let entries = u16::from_le_bytes([eocd[10], eocd[11]]);
+        assert_eq!(entries, 7);
+        let cd_offset = u32::from_le_bytes([eocd[16], eocd[17], eocd[18], eocd[19]]) as usize;
+        assert_eq!(bytes[cd_offset..cd_offset + 4], 0x02014b50u32.to_le_bytes());
+    }
+}